@@ -3,12 +3,17 @@ use crossterm::{
   execute,
   terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use rodio::{OutputStream, OutputStreamHandle};
-
+use crate::audio::AudioHandle;
+use crate::features::{order_by_similarity, smart_shuffle};
+use crate::lyrics::Lyrics;
+use crate::playlists;
 use crate::songs::{
-  get_search_dir, load_app_and_sink, load_song_list, search_songs, to_song_names,
+  get_search_dir, load_analyzer, load_song_list, search_songs, to_song_names, QueueItem,
 };
-use crate::ui::{add_songs_popup, get_ui_color, main_ui};
+use crate::spectrum::Analyzer;
+use crate::streaming::{self, Broadcaster, NetworkSource, StreamFrame, TrackHeader};
+use crate::ui::{add_songs_popup, get_ui_color, main_ui, playlist_menu};
+use rodio::{OutputStream, Sink};
 use std::{path::PathBuf, env};
 use std::str::FromStr;
 use std::{
@@ -18,17 +23,56 @@ use std::{
 };
 use tui::{
   backend::{Backend, CrosstermBackend},
+  widgets::ListState,
   Terminal, style::Color,
 };
 
 use anyhow::anyhow;
 
-/// Sets up the terminal, and runs the UI.
-pub fn run(song_path: Option<PathBuf>, use_default_color: bool) -> Result<(), Box<dyn Error>> {
+/// Sets up the terminal, and runs the UI. `max_samplerate` caps the rate
+/// every decoded source plays/visualizes at (see `crate::resample`).
+/// `smart_order` orders the initial queue by acoustic similarity instead of
+/// alphabetically (see `features::order_by_similarity`).
+pub fn run(
+  song_path: Option<PathBuf>,
+  use_default_color: bool,
+  max_samplerate: Option<u32>,
+  smart_order: bool,
+) -> Result<(), Box<dyn Error>> {
+  run_with_broadcaster(song_path, use_default_color, None, max_samplerate, smart_order)
+}
+
+/// Like `run`, but also serves the decoded PCM of whatever plays locally to
+/// any clients connected to `addr` (see `--listen`), turning this instance
+/// into a tiny personal radio station.
+pub fn run_serving(
+  song_path: Option<PathBuf>,
+  use_default_color: bool,
+  addr: &str,
+  max_samplerate: Option<u32>,
+  smart_order: bool,
+) -> Result<(), Box<dyn Error>> {
+  let broadcaster = Broadcaster::serve(addr)?;
+  run_with_broadcaster(
+    song_path,
+    use_default_color,
+    Some(broadcaster),
+    max_samplerate,
+    smart_order,
+  )
+}
+
+fn run_with_broadcaster(
+  song_path: Option<PathBuf>,
+  use_default_color: bool,
+  broadcaster: Option<Broadcaster>,
+  max_samplerate: Option<u32>,
+  smart_order: bool,
+) -> Result<(), Box<dyn Error>> {
   let config_dir = format!("{}/.config/tuitunes/", env::var("HOME")?);
   let config = format!("{}songs.txt", config_dir);
-  
-  let mut history: Vec<PathBuf> = vec![];
+
+  let mut history: Vec<QueueItem> = vec![];
   let mut play_next = match song_path {
     Some(p) => load_song_list(p)?,
     None => {
@@ -37,11 +81,11 @@ pub fn run(song_path: Option<PathBuf>, use_default_color: bool) -> Result<(), Bo
         Ok(s) => {
           s.split("\n")
             .filter(|s| !s.trim().is_empty())
-            .map(|s| PathBuf::from(s))
-            .collect::<Vec<PathBuf>>()
+            .map(|s| QueueItem::whole(PathBuf::from(s)))
+            .collect::<Vec<QueueItem>>()
         },
         Err(error) if error.kind() == io::ErrorKind::NotFound => {
-          std::fs::File::create(&config)?; 
+          std::fs::File::create(&config)?;
           vec![]
         }
         _ => {return Err(anyhow!("No path was provided, and failed to load any songs from config").into());}
@@ -49,16 +93,29 @@ pub fn run(song_path: Option<PathBuf>, use_default_color: bool) -> Result<(), Bo
     }
   };
 
+  if smart_order && !play_next.is_empty() {
+    play_next = order_by_similarity(play_next.as_slice(), &config_dir)?;
+  }
+
   // setup terminal
   enable_raw_mode()?;
   let mut stdout = io::stdout();
   execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
   let backend = CrosstermBackend::new(stdout);
   let mut terminal = Terminal::new(backend)?;
-  let (_stream, stream_handle) = OutputStream::try_default().unwrap();
-  
+  let audio = AudioHandle::spawn(max_samplerate)?;
+
   // run application
-  let res = run_app(&mut terminal, stream_handle, &mut play_next, &mut history, use_default_color);
+  let res = run_app(
+    &mut terminal,
+    audio,
+    &mut play_next,
+    &mut history,
+    use_default_color,
+    &config_dir,
+    broadcaster.as_ref(),
+    max_samplerate,
+  );
 
   // restore terminal
   disable_raw_mode()?;
@@ -70,7 +127,11 @@ pub fn run(song_path: Option<PathBuf>, use_default_color: bool) -> Result<(), Bo
   terminal.show_cursor()?;
 
   if !play_next.is_empty() && res.is_ok() {
-    if let Some(p) = play_next.iter().map(|s| s.to_str()).collect::<Option<Vec<&str>>>() {
+    if let Some(p) = play_next
+      .iter()
+      .map(|s| s.path.to_str())
+      .collect::<Option<Vec<&str>>>()
+    {
       Ok(std::fs::write(config, p.join("\n"))?)
     } else {
       println!("invalid paths");
@@ -85,10 +146,13 @@ pub fn run(song_path: Option<PathBuf>, use_default_color: bool) -> Result<(), Bo
 /// Runs the UI loop, assuming the terminal has been prepared.
 fn run_app<B: Backend>(
   terminal: &mut Terminal<B>,
-  stream_handle: OutputStreamHandle,
-  play_next: &mut Vec<PathBuf>,
-  history: &mut Vec<PathBuf>,
+  audio: AudioHandle,
+  play_next: &mut Vec<QueueItem>,
+  history: &mut Vec<QueueItem>,
   use_default_color: bool,
+  config_dir: &str,
+  broadcaster: Option<&Broadcaster>,
+  max_samplerate: Option<u32>,
 ) -> Result<(), Box<dyn std::error::Error>> {
   let tick_rate = Duration::from_millis(crate::TICK_RATE);
 
@@ -107,25 +171,40 @@ fn run_app<B: Backend>(
     }
     let song = play_next.pop().unwrap();
 
-    let maybe_song_data = load_app_and_sink(&song, &stream_handle);
-    if let Err(e) = &maybe_song_data {
+    let maybe_analyzer = load_analyzer(&song.path, song.start, max_samplerate);
+    if let Err(e) = &maybe_analyzer {
       eprintln!("could not load song, skipping...: {}", e);
       continue; // skip to next song
     }
-    let (mut analyzer, mut sink) = maybe_song_data.unwrap();
+    let mut analyzer = maybe_analyzer.unwrap();
+    audio.set_source(song.path.clone(), song.start)?;
 
-    let song_name = song.file_stem().unwrap().to_str().unwrap();
-    let ui_color = get_ui_color(song_name, use_default_color);
+    let song_name = song.display_name();
+    let now_playing_label = song.now_playing_label();
+    let ui_color = get_ui_color(&song_name, use_default_color);
+    let lyrics = Lyrics::load(&song.path);
+    if let Some(b) = broadcaster {
+      b.send_header(TrackHeader {
+        name: song_name.clone(),
+        sample_rate: analyzer.sample_rate(),
+        channels: analyzer.channels() as u16,
+      });
+    }
     let mut last_tick = Instant::now();
+    let mut play_time = Duration::from_secs(0);
+    let mut paused = false;
     'song: loop {
       terminal.draw(|f| {
         main_ui(
           f,
           &analyzer,
-          song_name,
-          &to_song_names(&play_next, true),
-          &to_song_names(&history, false),
+          &now_playing_label,
+          &to_song_names(play_next.as_slice(), true),
+          &to_song_names(history.as_slice(), false),
           ui_color,
+          lyrics.as_ref(),
+          play_time,
+          song.tag_duration,
         )
       })?;
 
@@ -149,19 +228,39 @@ fn run_app<B: Backend>(
               break 'song;
             }
             KeyCode::Char('p') => {
-              if sink.is_paused() {
-                sink.play();
+              if paused {
+                audio.play()?;
                 last_tick = Instant::now();
               } else {
-                sink.pause();
+                audio.pause()?;
               }
+              paused = !paused;
             }
             KeyCode::Char('r') => {
-              sink.stop();
-              (analyzer, sink) = load_app_and_sink(&song, &stream_handle)?
+              audio.set_source(song.path.clone(), song.start)?;
+              analyzer = load_analyzer(&song.path, song.start, max_samplerate)?;
+              play_time = Duration::from_secs(0);
+              paused = false;
+            }
+            // Seek ±5s. rodio's `Sink` can't seek mid-stream, so (like `r`
+            // above) this re-decodes the file from the target offset and
+            // replaces both the sink and the `Analyzer`'s source.
+            KeyCode::Left => {
+              let target = play_time.saturating_sub(Duration::from_secs(5));
+              analyzer = seek_to(&audio, &song, target, max_samplerate)?;
+              play_time = target;
+            }
+            KeyCode::Right => {
+              let mut target = play_time + Duration::from_secs(5);
+              if let Some(d) = song.duration.or(song.tag_duration) {
+                target = target.min(d);
+              }
+              analyzer = seek_to(&audio, &song, target, max_samplerate)?;
+              play_time = target;
             }
             KeyCode::Char('a') => {
-              sink.pause();
+              audio.pause()?;
+              paused = true;
               match submit_more_songs(terminal, ui_color)? {
                 Some(buf) => {
                   let mut new_song_list = load_song_list(PathBuf::from_str(&buf)?)?;
@@ -171,7 +270,8 @@ fn run_app<B: Backend>(
                   break 'song;
                 }
                 None => {
-                  sink.play();
+                  audio.play()?;
+                  paused = false;
                   last_tick = Instant::now();
                 }
               };
@@ -181,19 +281,248 @@ fn run_app<B: Backend>(
               fastrand::shuffle(play_next);
               break 'song;
             }
+            // Smart shuffle: order the queue by acoustic similarity to the
+            // current song instead of pure randomness.
+            KeyCode::Char('S') => {
+              *play_next = smart_shuffle(play_next.as_slice(), &song, config_dir)?;
+              history.push(song);
+              break 'song;
+            }
+            KeyCode::Char('l') => {
+              audio.pause()?;
+              paused = true;
+              match run_playlist_menu(terminal, config_dir, play_next, ui_color)? {
+                Some(PlaylistChoice::Load(mut items)) => {
+                  play_next.clear();
+                  play_next.append(&mut items);
+                  play_next.push(song);
+                  break 'song;
+                }
+                Some(PlaylistChoice::Append(mut items)) => {
+                  play_next.append(&mut items);
+                  audio.play()?;
+                  paused = false;
+                  last_tick = Instant::now();
+                }
+                None => {
+                  audio.play()?;
+                  paused = false;
+                  last_tick = Instant::now();
+                }
+              }
+            }
             _ => (),
           }
         }
       }
-      if sink.empty() {
+      if audio.poll_complete() || song.duration.is_some_and(|d| play_time >= d) {
+        audio.stop()?;
         history.push(song);
         break 'song;
       }
-      if !sink.is_paused() && last_tick.elapsed() >= tick_rate {
-        let elapsed = last_tick.elapsed().as_millis();
+      if !paused && last_tick.elapsed() >= tick_rate {
+        let elapsed = last_tick.elapsed();
         last_tick = Instant::now();
-        analyzer.sample_audio(elapsed as u32);
+        play_time += elapsed;
+        analyzer.sample_audio(elapsed.as_millis() as u32);
+        if let Some(b) = broadcaster {
+          b.send_samples(analyzer.last_samples());
+        }
+      }
+    }
+  }
+}
+
+/// Sets up the terminal and runs as a `--listen` client: connects to a
+/// `--serve` instance and plays/visualizes whatever it streams, using the
+/// same `Analyzer`/`Sink` machinery as local playback.
+pub fn run_client(addr: &str, use_default_color: bool) -> Result<(), Box<dyn Error>> {
+  let stream = streaming::connect(addr)?;
+  let frame_rx = streaming::spawn_reader(stream);
+
+  enable_raw_mode()?;
+  let mut stdout = io::stdout();
+  execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+  let backend = CrosstermBackend::new(stdout);
+  let mut terminal = Terminal::new(backend)?;
+
+  let res = run_client_loop(&mut terminal, frame_rx, use_default_color);
+
+  disable_raw_mode()?;
+  execute!(
+    terminal.backend_mut(),
+    LeaveAlternateScreen,
+    DisableMouseCapture
+  )?;
+  terminal.show_cursor()?;
+
+  res
+}
+
+fn run_client_loop<B: Backend>(
+  terminal: &mut Terminal<B>,
+  frame_rx: std::sync::mpsc::Receiver<StreamFrame>,
+  use_default_color: bool,
+) -> Result<(), Box<dyn Error>> {
+  let tick_rate = Duration::from_millis(crate::TICK_RATE);
+  let (_stream, stream_handle) = OutputStream::try_default().unwrap();
+
+  let mut song_name = "waiting for stream...".to_owned();
+  let mut ui_color = get_ui_color(&song_name, use_default_color);
+  let mut analyzer = Analyzer::for_streaming(44_100, 2);
+  let mut sink: Option<Sink> = None;
+  let mut sample_tx: Option<std::sync::mpsc::Sender<f32>> = None;
+  let mut play_time = Duration::from_secs(0);
+  let mut last_tick = Instant::now();
+
+  loop {
+    terminal.draw(|f| {
+      main_ui(
+        f, &analyzer, &song_name, &[], &[], ui_color, None, play_time, None,
+      )
+    })?;
+
+    let timeout = tick_rate
+      .checked_sub(last_tick.elapsed())
+      .unwrap_or_else(|| Duration::from_secs(0));
+    if crossterm::event::poll(timeout)? {
+      if let Event::Key(key) = event::read()? {
+        if key.code == KeyCode::Char('q') {
+          return Ok(());
+        }
+      }
+    }
+    last_tick = Instant::now();
+
+    for frame in frame_rx.try_iter() {
+      match frame {
+        StreamFrame::Header(header) => {
+          if let Some(old) = sink.take() {
+            old.stop();
+          }
+
+          song_name = header.name.clone();
+          ui_color = get_ui_color(&song_name, use_default_color);
+          analyzer = Analyzer::for_streaming(header.sample_rate, header.channels);
+          play_time = Duration::from_secs(0);
+
+          let (tx, rx) = std::sync::mpsc::channel();
+          let source = NetworkSource::new(rx, header.sample_rate, header.channels);
+          let new_sink = Sink::try_new(&stream_handle)?;
+          new_sink.append(source);
+          sink = Some(new_sink);
+          sample_tx = Some(tx);
+        }
+        StreamFrame::Samples(samples) => {
+          analyzer.feed_samples(&samples);
+          let frame_rate = analyzer.channels().max(1) as f32 * analyzer.sample_rate() as f32;
+          play_time += Duration::from_secs_f32(samples.len() as f32 / frame_rate);
+          if let Some(tx) = &sample_tx {
+            for s in samples {
+              let _ = tx.send(s);
+            }
+          }
+        }
+      }
+    }
+  }
+}
+
+/// Seeks to `track_offset` into `song` (relative to its own start, not the
+/// underlying file's), re-decoding both the playback sink and the analyzer
+/// from there since `rodio::Sink` can't seek mid-stream.
+fn seek_to<'a>(
+  audio: &AudioHandle,
+  song: &'a QueueItem,
+  track_offset: Duration,
+  max_samplerate: Option<u32>,
+) -> Result<Analyzer<'a>, Box<dyn Error>> {
+  let absolute_offset = song.start + track_offset;
+  audio.set_source(song.path.clone(), absolute_offset)?;
+  load_analyzer(&song.path, absolute_offset, max_samplerate)
+}
+
+/// What the user picked in the playlist menu (see `run_playlist_menu`).
+enum PlaylistChoice {
+  /// Replace the running queue with this playlist.
+  Load(Vec<QueueItem>),
+  /// Add this playlist's songs on top of the running queue.
+  Append(Vec<QueueItem>),
+}
+
+/// Drives the `l` playlist popup: lets the user arrow/`j`/`k` through saved
+/// playlists and load or append one, or type a name and save `current_queue`
+/// as a new playlist.
+fn run_playlist_menu<B: Backend>(
+  terminal: &mut Terminal<B>,
+  config_dir: &str,
+  current_queue: &[QueueItem],
+  ui_color: Color,
+) -> Result<Option<PlaylistChoice>, Box<dyn Error>> {
+  let mut state = ListState::default();
+  let mut saving = false;
+  let mut new_name = String::new();
+
+  loop {
+    let names = playlists::list(config_dir)?;
+    if state.selected().is_none() && !names.is_empty() {
+      state.select(Some(0));
+    }
+
+    terminal.draw(|f| playlist_menu(f, &names, &mut state, &new_name, ui_color))?;
+
+    let Event::Key(key) = event::read()? else {
+      continue;
+    };
+
+    if saving {
+      match key.code {
+        KeyCode::Enter => {
+          if !new_name.trim().is_empty() {
+            playlists::save(config_dir, new_name.trim(), current_queue)?;
+          }
+          saving = false;
+          new_name.clear();
+        }
+        KeyCode::Esc => {
+          saving = false;
+          new_name.clear();
+        }
+        KeyCode::Backspace => {
+          new_name.pop();
+        }
+        KeyCode::Char(c) => new_name.push(c),
+        _ => (),
+      }
+      continue;
+    }
+
+    match key.code {
+      KeyCode::Esc => return Ok(None),
+      KeyCode::Char('s') => saving = true,
+      KeyCode::Char('j') | KeyCode::Down => {
+        let i = state.selected().unwrap_or(0);
+        if i + 1 < names.len() {
+          state.select(Some(i + 1));
+        }
+      }
+      KeyCode::Char('k') | KeyCode::Up => {
+        let i = state.selected().unwrap_or(0);
+        state.select(Some(i.saturating_sub(1)));
+      }
+      KeyCode::Enter => {
+        if let Some(name) = state.selected().and_then(|i| names.get(i)) {
+          return Ok(Some(PlaylistChoice::Load(playlists::load(config_dir, name)?)));
+        }
+      }
+      KeyCode::Char('a') => {
+        if let Some(name) = state.selected().and_then(|i| names.get(i)) {
+          return Ok(Some(PlaylistChoice::Append(playlists::load(
+            config_dir, name,
+          )?)));
+        }
       }
+      _ => (),
     }
   }
 }
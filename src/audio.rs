@@ -0,0 +1,143 @@
+use std::{
+  error::Error,
+  io::BufReader,
+  fs::File,
+  path::PathBuf,
+  sync::mpsc::{self, Receiver, RecvTimeoutError, Sender},
+  thread,
+  time::Duration,
+};
+
+use rodio::{source::Source, Decoder, OutputStream, OutputStreamHandle, Sink};
+
+/// Commands sent to the audio thread to drive playback.
+pub enum PlayCmd {
+  Play,
+  Pause,
+  Stop,
+  /// Load the given file and start playing it `start` into the track.
+  SetSource(PathBuf, Duration),
+}
+
+/// Notifications sent back from the audio thread.
+pub enum PlayEvent {
+  /// The currently loaded track finished playing on its own.
+  TrackComplete,
+}
+
+/// Owns the channel endpoints used to talk to the dedicated audio thread.
+/// The thread itself owns the `OutputStream`/`Sink`, so a failed device or a
+/// bad decode can't poison the UI loop.
+pub struct AudioHandle {
+  cmd_tx: Sender<PlayCmd>,
+  event_rx: Receiver<PlayEvent>,
+}
+
+impl AudioHandle {
+  /// Spawns the audio thread and returns a handle to communicate with it.
+  /// `max_samplerate` caps the rate of every source it decodes (see
+  /// `crate::resample`).
+  pub fn spawn(max_samplerate: Option<u32>) -> Result<AudioHandle, Box<dyn Error>> {
+    let (cmd_tx, cmd_rx) = mpsc::channel();
+    let (event_tx, event_rx) = mpsc::channel();
+
+    thread::Builder::new()
+      .name("tuitunes-audio".to_owned())
+      .spawn(move || audio_thread(cmd_rx, event_tx, max_samplerate))?;
+
+    Ok(AudioHandle { cmd_tx, event_rx })
+  }
+
+  /// Replaces the currently playing source and starts playback `start` into
+  /// the track (use `Duration::ZERO` to play from the beginning).
+  pub fn set_source(&self, song: PathBuf, start: Duration) -> Result<(), Box<dyn Error>> {
+    Ok(self.cmd_tx.send(PlayCmd::SetSource(song, start))?)
+  }
+
+  pub fn play(&self) -> Result<(), Box<dyn Error>> {
+    Ok(self.cmd_tx.send(PlayCmd::Play)?)
+  }
+
+  pub fn pause(&self) -> Result<(), Box<dyn Error>> {
+    Ok(self.cmd_tx.send(PlayCmd::Pause)?)
+  }
+
+  pub fn stop(&self) -> Result<(), Box<dyn Error>> {
+    Ok(self.cmd_tx.send(PlayCmd::Stop)?)
+  }
+
+  /// Non-blocking check for a "track complete" notification pushed by the
+  /// audio thread, so the UI loop can advance `history`/`play_next` without
+  /// polling `sink.empty()` itself.
+  pub fn poll_complete(&self) -> bool {
+    self
+      .event_rx
+      .try_iter()
+      .any(|e| matches!(e, PlayEvent::TrackComplete))
+  }
+}
+
+/// Body of the dedicated audio thread: owns the `OutputStream`/`Sink` and
+/// applies commands as they arrive, pushing a notification back once the
+/// loaded track finishes on its own.
+fn audio_thread(cmd_rx: Receiver<PlayCmd>, event_tx: Sender<PlayEvent>, max_samplerate: Option<u32>) {
+  let (_stream, stream_handle) = match OutputStream::try_default() {
+    Ok(s) => s,
+    Err(_) => return,
+  };
+  let mut sink: Option<Sink> = None;
+
+  loop {
+    match cmd_rx.recv_timeout(Duration::from_millis(50)) {
+      Ok(PlayCmd::SetSource(song, start)) => {
+        sink = load_sink(&stream_handle, &song, start, max_samplerate).ok();
+      }
+      Ok(PlayCmd::Play) => {
+        if let Some(s) = &sink {
+          s.play();
+        }
+      }
+      Ok(PlayCmd::Pause) => {
+        if let Some(s) = &sink {
+          s.pause();
+        }
+      }
+      Ok(PlayCmd::Stop) => {
+        if let Some(s) = sink.take() {
+          s.stop();
+        }
+      }
+      Err(RecvTimeoutError::Timeout) => (),
+      Err(RecvTimeoutError::Disconnected) => return,
+    }
+
+    if let Some(s) = &sink {
+      if s.empty() {
+        sink = None;
+        if event_tx.send(PlayEvent::TrackComplete).is_err() {
+          return;
+        }
+      }
+    }
+  }
+}
+
+/// Decodes `song`, skips `start` into it, and appends it to a fresh `Sink`.
+/// Re-decoding (rather than seeking an existing `Sink`, which `rodio` can't
+/// do mid-stream) is also how CUE track boundaries and restarts are handled.
+fn load_sink(
+  stream_handle: &OutputStreamHandle,
+  song: &PathBuf,
+  start: Duration,
+  max_samplerate: Option<u32>,
+) -> Result<Sink, Box<dyn Error>> {
+  let file = BufReader::new(File::open(song)?);
+  let source = Decoder::new(file)?
+    .convert_samples::<f32>()
+    .skip_duration(start);
+  let source = crate::resample::cap_sample_rate(source, max_samplerate);
+
+  let sink = Sink::try_new(stream_handle)?;
+  sink.append(source);
+  Ok(sink)
+}
@@ -0,0 +1,188 @@
+use std::{
+  error::Error,
+  fs,
+  path::{Path, PathBuf},
+  time::Duration,
+};
+
+use anyhow::anyhow;
+
+use crate::songs::QueueItem;
+
+/// A single `TRACK` entry parsed out of a CUE sheet, before its end boundary
+/// (the next track's start, or EOF) is known.
+struct CueTrack {
+  title: Option<String>,
+  /// Per-track `PERFORMER`, if the sheet overrides the album-level one.
+  performer: Option<String>,
+  start: Duration,
+}
+
+/// Parses a `.cue` sheet and expands it into one `QueueItem` per `TRACK`,
+/// all pointing at the sheet's `FILE` with the track's `INDEX 01` start
+/// offset, and a `duration` running up to the next track's start (or `None`
+/// for the last track, which plays to EOF).
+pub fn expand_cue_sheet(cue_path: &Path) -> Result<Vec<QueueItem>, Box<dyn Error>> {
+  let contents = fs::read_to_string(cue_path)?;
+  let dir = cue_path.parent().unwrap_or_else(|| Path::new("."));
+
+  let mut audio_path: Option<PathBuf> = None;
+  let mut album_title: Option<String> = None;
+  let mut album_performer: Option<String> = None;
+  let mut tracks: Vec<CueTrack> = vec![];
+
+  for line in contents.lines() {
+    let line = line.trim();
+    if let Some(rest) = line.strip_prefix("FILE ") {
+      if let Some(name) = quoted(rest) {
+        audio_path = Some(dir.join(name));
+      }
+    } else if line.starts_with("TRACK ") {
+      tracks.push(CueTrack {
+        title: None,
+        performer: None,
+        start: Duration::ZERO,
+      });
+    } else if let Some(rest) = line.strip_prefix("TITLE ") {
+      // Before the first TRACK, TITLE names the whole album; inside one,
+      // it names just that track.
+      match (quoted(rest), tracks.last_mut()) {
+        (Some(title), Some(track)) => track.title = Some(title),
+        (Some(title), None) => album_title = Some(title),
+        (None, _) => {}
+      }
+    } else if let Some(rest) = line.strip_prefix("PERFORMER ") {
+      if let Some(performer) = quoted(rest) {
+        // Before the first TRACK, PERFORMER names the whole album; inside
+        // one, it overrides that for just this track (e.g. a compilation).
+        match tracks.last_mut() {
+          Some(track) => track.performer = Some(performer),
+          None => album_performer = Some(performer),
+        }
+      }
+    } else if let Some(rest) = line.strip_prefix("INDEX 01 ") {
+      if let (Some(start), Some(track)) = (parse_timestamp(rest.trim()), tracks.last_mut()) {
+        track.start = start;
+      }
+    }
+  }
+
+  let audio_path =
+    audio_path.ok_or_else(|| anyhow!("cue sheet {:?} has no FILE entry", cue_path))?;
+
+  // Fall back to the whole file's embedded tags if the sheet itself has no
+  // PERFORMER/album TITLE. `duration` (the CUE boundary) and `tag_duration`
+  // (the progress gauge) are deliberately kept separate.
+  let file_tags = crate::tags::read(&audio_path);
+  let album = album_title.or(file_tags.album);
+
+  let items = tracks
+    .iter()
+    .enumerate()
+    .map(|(i, track)| QueueItem {
+      path: audio_path.clone(),
+      start: track.start,
+      // `checked_sub` guards against a hand-edited or buggy-ripper sheet
+      // whose `INDEX 01` timestamps aren't strictly increasing; such a
+      // track just plays with no known end (same as the last track).
+      duration: tracks
+        .get(i + 1)
+        .and_then(|next| next.start.checked_sub(track.start)),
+      title: track.title.clone(),
+      artist: track
+        .performer
+        .clone()
+        .or_else(|| album_performer.clone())
+        .or_else(|| file_tags.artist.clone()),
+      album: album.clone(),
+      tag_duration: None,
+    })
+    .collect();
+
+  Ok(items)
+}
+
+/// Pulls the contents of a `"quoted string"`.
+fn quoted(s: &str) -> Option<String> {
+  let s = s.trim().strip_prefix('"')?;
+  let s = s.strip_suffix('"')?;
+  Some(s.to_owned())
+}
+
+/// Parses a CUE `MM:SS:FF` timestamp, where `FF` is frames at 75 frames/sec.
+fn parse_timestamp(s: &str) -> Option<Duration> {
+  let mut parts = s.split(':');
+  let mm: u64 = parts.next()?.parse().ok()?;
+  let ss: u64 = parts.next()?.parse().ok()?;
+  let ff: u64 = parts.next()?.parse().ok()?;
+  Some(Duration::from_millis(mm * 60_000 + ss * 1000 + ff * 1000 / 75))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parse_timestamp_reads_minutes_seconds_frames() {
+    assert_eq!(parse_timestamp("01:02:37"), Some(Duration::from_millis(62_493)));
+    assert_eq!(parse_timestamp("00:00:00"), Some(Duration::ZERO));
+  }
+
+  #[test]
+  fn parse_timestamp_rejects_malformed_input() {
+    assert_eq!(parse_timestamp(""), None);
+    assert_eq!(parse_timestamp("01:02"), None);
+    assert_eq!(parse_timestamp("aa:bb:cc"), None);
+  }
+
+  fn write_temp_cue(contents: &str) -> PathBuf {
+    let mut path = std::env::temp_dir();
+    path.push(format!(
+      "tuitunes-test-{}-{}.cue",
+      std::process::id(),
+      contents.len()
+    ));
+    fs::write(&path, contents).unwrap();
+    path
+  }
+
+  #[test]
+  fn expand_cue_sheet_computes_durations_between_tracks() {
+    let path = write_temp_cue(
+      "FILE \"album.flac\"\n\
+       TITLE \"Album\"\n\
+       TRACK 01 AUDIO\n\
+       TITLE \"One\"\n\
+       INDEX 01 00:00:00\n\
+       TRACK 02 AUDIO\n\
+       TITLE \"Two\"\n\
+       INDEX 01 03:00:00\n",
+    );
+
+    let items = expand_cue_sheet(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].duration, Some(Duration::from_secs(180)));
+    assert_eq!(items[1].duration, None);
+  }
+
+  #[test]
+  fn expand_cue_sheet_survives_out_of_order_index_01() {
+    // A hand-edited/buggy-ripper sheet where track 2 starts before track 1
+    // must not panic on the `next.start - track.start` subtraction.
+    let path = write_temp_cue(
+      "FILE \"album.flac\"\n\
+       TRACK 01 AUDIO\n\
+       INDEX 01 03:00:00\n\
+       TRACK 02 AUDIO\n\
+       INDEX 01 01:00:00\n",
+    );
+
+    let items = expand_cue_sheet(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].duration, None);
+  }
+}
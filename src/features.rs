@@ -0,0 +1,435 @@
+use std::{
+  collections::HashMap,
+  error::Error,
+  fs,
+  io::BufReader,
+  path::{Path, PathBuf},
+  time::SystemTime,
+};
+
+use rodio::{Decoder, Source};
+use spectrum_analyzer::{
+  samples_fft_to_spectrum, scaling::divide_by_N, windows::hann_window, FrequencyLimit,
+};
+
+use anyhow::anyhow;
+
+use crate::songs::QueueItem;
+
+const NUM_BANDS: usize = 6;
+const NUM_CHROMA: usize = 12;
+const FRAME_SIZE: usize = 2048;
+/// Excerpt length decoded by `extract`, since the acoustic character of a
+/// song is well captured by its first half-minute and this keeps
+/// `--smart-order`/`smart_shuffle` fast over a large library.
+const EXCERPT_SECS: f32 = 30.0;
+/// Tempo search range, in BPM, for the autocorrelation estimate in `extract`.
+const MIN_BPM: u32 = 60;
+const MAX_BPM: u32 = 180;
+
+/// A fixed-length descriptor summarizing a song's acoustic character, used to
+/// order "smart shuffle"/`--smart-order` playback by similarity instead of
+/// pure randomness.
+#[derive(Clone, Debug)]
+pub struct Descriptor {
+  pub onset_rate: f32,
+  pub spectral_centroid: f32,
+  pub spectral_rolloff: f32,
+  pub zero_crossing_rate: f32,
+  pub tempo_bpm: f32,
+  pub log_bands: [f32; NUM_BANDS],
+  pub chroma: [f32; NUM_CHROMA],
+}
+
+impl Descriptor {
+  fn as_vec(&self) -> Vec<f32> {
+    let mut v = vec![
+      self.onset_rate,
+      self.spectral_centroid,
+      self.spectral_rolloff,
+      self.zero_crossing_rate,
+      self.tempo_bpm,
+    ];
+    v.extend_from_slice(&self.log_bands);
+    v.extend_from_slice(&self.chroma);
+    v
+  }
+
+  fn from_vec(v: &[f32]) -> Descriptor {
+    let mut log_bands = [0.0; NUM_BANDS];
+    log_bands.copy_from_slice(&v[5..5 + NUM_BANDS]);
+    let mut chroma = [0.0; NUM_CHROMA];
+    chroma.copy_from_slice(&v[5 + NUM_BANDS..5 + NUM_BANDS + NUM_CHROMA]);
+    Descriptor {
+      onset_rate: v[0],
+      spectral_centroid: v[1],
+      spectral_rolloff: v[2],
+      zero_crossing_rate: v[3],
+      tempo_bpm: v[4],
+      log_bands,
+      chroma,
+    }
+  }
+
+  /// Number of scalars `as_vec`/`from_vec` round-trip, used to recognize and
+  /// discard cache entries written by an older descriptor shape.
+  fn vec_len() -> usize {
+    5 + NUM_BANDS + NUM_CHROMA
+  }
+}
+
+/// Decodes up to `EXCERPT_SECS` of `path` (mixed down to mono) and computes
+/// its descriptor vector, using the same FFT pipeline as `spectrum::Analyzer`:
+/// onset rate, spectral centroid/rolloff, zero-crossing rate, a 12-bin chroma
+/// vector, a tempo estimate, and averaged log-spaced band energies (binned
+/// the same way as `spectrum::Analyzer::compute_spectrum`).
+pub fn extract(path: &Path) -> Result<Descriptor, Box<dyn Error>> {
+  let file = BufReader::new(fs::File::open(path)?);
+  let source = Decoder::new(file)?.convert_samples::<f32>();
+  let sample_rate = source.sample_rate();
+  let channels = source.channels().max(1) as usize;
+  let max_mono_samples = (EXCERPT_SECS * sample_rate as f32) as usize;
+  let interleaved = source
+    .take(max_mono_samples * channels)
+    .collect::<Vec<f32>>();
+  let samples = interleaved
+    .chunks(channels)
+    .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+    .collect::<Vec<f32>>();
+
+  let zero_crossings = samples
+    .windows(2)
+    .filter(|w| (w[0] >= 0.0) != (w[1] >= 0.0))
+    .count();
+  let zero_crossing_rate = zero_crossings as f32 / samples.len().max(1) as f32;
+
+  let mut centroid_sum = 0.0f32;
+  let mut centroid_weight = 0.0f32;
+  let mut band_energy = [0.0f32; NUM_BANDS];
+  let mut chroma = [0.0f32; NUM_CHROMA];
+  // Total magnitude seen at each (rounded) frequency, across all frames, to
+  // compute the spectral rolloff over the whole excerpt rather than per-frame.
+  let mut freq_energy: std::collections::BTreeMap<i64, f32> = std::collections::BTreeMap::new();
+  let mut onset_count = 0usize;
+  let mut prev_energy = 0.0f32;
+  let mut frame_energies: Vec<f32> = Vec::new();
+
+  for chunk in samples.chunks(FRAME_SIZE) {
+    if chunk.len() < FRAME_SIZE / 2 {
+      continue;
+    }
+    let mut buf = chunk.to_vec();
+    buf.resize(FRAME_SIZE, 0.0);
+    let windowed = hann_window(&buf);
+    let spectrum = samples_fft_to_spectrum(
+      &windowed,
+      sample_rate,
+      FrequencyLimit::Range(40.0, 5000.0),
+      Some(&divide_by_N),
+    )
+    .map_err(|e| anyhow!("fft failed: {:?}", e))?;
+
+    let mut frame_energy = 0.0f32;
+    for (fr, val) in spectrum.data().iter() {
+      let f = fr.val();
+      let v = val.val();
+      centroid_sum += f * v;
+      centroid_weight += v;
+      frame_energy += v;
+
+      // Log-spaced, matching `spectrum::Analyzer::compute_spectrum`'s binning.
+      let band = (NUM_BANDS as f32 * (f / 40.0f32).ln() / (5000.0f32 / 40.0).ln()) as isize;
+      let band = band.clamp(0, NUM_BANDS as isize - 1) as usize;
+      band_energy[band] += v;
+
+      *freq_energy.entry(f.round() as i64).or_insert(0.0) += v;
+
+      // Fold into a pitch class relative to A4 (440Hz, MIDI-style offset 9
+      // so pitch class 0 lands on C).
+      let pitch_class = ((12.0 * (f / 440.0).log2()).round() as i64 + 9).rem_euclid(12) as usize;
+      chroma[pitch_class] += v;
+    }
+
+    if frame_energy > prev_energy * 1.3 {
+      onset_count += 1;
+    }
+    prev_energy = frame_energy;
+    frame_energies.push(frame_energy);
+  }
+
+  let frame_count = frame_energies.len().max(1);
+  for e in band_energy.iter_mut() {
+    *e /= frame_count as f32;
+  }
+  for c in chroma.iter_mut() {
+    *c /= frame_count as f32;
+  }
+
+  let total_energy: f32 = freq_energy.values().sum();
+  let mut cumulative = 0.0f32;
+  let mut spectral_rolloff = 0.0f32;
+  for (&freq, &e) in freq_energy.iter() {
+    cumulative += e;
+    if total_energy > 0.0 && cumulative >= 0.85 * total_energy {
+      spectral_rolloff = freq as f32;
+      break;
+    }
+  }
+
+  let duration_secs = (samples.len() as f32 / sample_rate.max(1) as f32).max(1.0);
+
+  Ok(Descriptor {
+    onset_rate: onset_count as f32 / duration_secs,
+    spectral_centroid: if centroid_weight > 0.0 {
+      centroid_sum / centroid_weight
+    } else {
+      0.0
+    },
+    spectral_rolloff,
+    zero_crossing_rate,
+    tempo_bpm: estimate_tempo(&frame_energies, sample_rate),
+    log_bands: band_energy,
+    chroma,
+  })
+}
+
+/// Estimates tempo by autocorrelating the frame-energy envelope at the lag
+/// corresponding to each candidate BPM in `[MIN_BPM, MAX_BPM]`, picking the
+/// lag with maximum correlation.
+fn estimate_tempo(frame_energies: &[f32], sample_rate: u32) -> f32 {
+  let hop_secs = FRAME_SIZE as f32 / sample_rate.max(1) as f32;
+
+  let mut best_bpm = MIN_BPM as f32;
+  let mut best_corr = f32::MIN;
+  for bpm in MIN_BPM..=MAX_BPM {
+    let lag = ((60.0 / bpm as f32) / hop_secs).round() as usize;
+    if lag == 0 || lag >= frame_energies.len() {
+      continue;
+    }
+    let corr = frame_energies
+      .iter()
+      .zip(frame_energies[lag..].iter())
+      .map(|(a, b)| a * b)
+      .sum::<f32>();
+    if corr > best_corr {
+      best_corr = corr;
+      best_bpm = bpm as f32;
+    }
+  }
+  best_bpm
+}
+
+/// Path of the on-disk descriptor cache, kept alongside the rest of
+/// tuitunes's config.
+fn cache_path(config_dir: &str) -> PathBuf {
+  PathBuf::from(format!("{}features_cache.txt", config_dir))
+}
+
+/// Loads the descriptor cache, keyed by song path, as `(mtime, Descriptor)`.
+/// Missing/unreadable cache files, and entries written by an older
+/// descriptor shape, are just dropped in favor of recomputing.
+pub fn load_cache(config_dir: &str) -> HashMap<PathBuf, (u64, Descriptor)> {
+  let mut cache = HashMap::new();
+  let Ok(contents) = fs::read_to_string(cache_path(config_dir)) else {
+    return cache;
+  };
+  for line in contents.lines() {
+    let mut parts = line.split('\t');
+    let (Some(path), Some(mtime), Some(values)) = (parts.next(), parts.next(), parts.next())
+    else {
+      continue;
+    };
+    let Ok(mtime) = mtime.parse::<u64>() else {
+      continue;
+    };
+    let values = values
+      .split(',')
+      .filter_map(|s| s.parse::<f32>().ok())
+      .collect::<Vec<f32>>();
+    if values.len() != Descriptor::vec_len() {
+      continue;
+    }
+    cache.insert(PathBuf::from(path), (mtime, Descriptor::from_vec(&values)));
+  }
+  cache
+}
+
+/// Persists the descriptor cache back to disk.
+pub fn save_cache(
+  config_dir: &str,
+  cache: &HashMap<PathBuf, (u64, Descriptor)>,
+) -> Result<(), Box<dyn Error>> {
+  let mut out = String::new();
+  for (path, (mtime, descriptor)) in cache {
+    let values = descriptor
+      .as_vec()
+      .iter()
+      .map(|v| v.to_string())
+      .collect::<Vec<String>>()
+      .join(",");
+    out.push_str(&format!(
+      "{}\t{}\t{}\n",
+      path.to_str().unwrap_or_default(),
+      mtime,
+      values
+    ));
+  }
+  fs::write(cache_path(config_dir), out)?;
+  Ok(())
+}
+
+fn mtime_secs(path: &Path) -> u64 {
+  fs::metadata(path)
+    .and_then(|m| m.modified())
+    .ok()
+    .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+    .map(|d| d.as_secs())
+    .unwrap_or(0)
+}
+
+/// Returns the cached descriptor for `path`, recomputing it if the file is
+/// new or its mtime has changed since it was cached.
+pub fn get_or_compute(
+  path: &Path,
+  cache: &mut HashMap<PathBuf, (u64, Descriptor)>,
+) -> Result<Descriptor, Box<dyn Error>> {
+  let mtime = mtime_secs(path);
+  if let Some((cached_mtime, descriptor)) = cache.get(path) {
+    if *cached_mtime == mtime {
+      return Ok(descriptor.clone());
+    }
+  }
+  let descriptor = extract(path)?;
+  cache.insert(path.to_path_buf(), (mtime, descriptor.clone()));
+  Ok(descriptor)
+}
+
+/// Greedily walks from `anchor` to the nearest remaining neighbor in
+/// `vectors` (Euclidean distance) until `rest` is exhausted, returning the
+/// resulting play order (`anchor` first).
+fn greedy_order(
+  anchor: &QueueItem,
+  rest: &[QueueItem],
+  vectors: &HashMap<PathBuf, Vec<f32>>,
+) -> Vec<QueueItem> {
+  let mut unvisited = rest.to_vec();
+  let mut order = vec![anchor.clone()];
+  let mut last_vec = vectors[&anchor.path].clone();
+  while !unvisited.is_empty() {
+    let (idx, _) = unvisited
+      .iter()
+      .enumerate()
+      .map(|(i, item)| (i, euclidean_distance(&last_vec, &vectors[&item.path])))
+      .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap())
+      .unwrap();
+    let next = unvisited.remove(idx);
+    last_vec = vectors[&next.path].clone();
+    order.push(next);
+  }
+  order
+}
+
+/// Computes (and caches) a descriptor vector for every distinct path across
+/// `anchor` and `rest`.
+fn vectors_for(
+  anchor: &QueueItem,
+  rest: &[QueueItem],
+  config_dir: &str,
+) -> Result<HashMap<PathBuf, Vec<f32>>, Box<dyn Error>> {
+  let mut cache = load_cache(config_dir);
+  let mut vectors: HashMap<PathBuf, Vec<f32>> = HashMap::new();
+  vectors.insert(
+    anchor.path.clone(),
+    get_or_compute(&anchor.path, &mut cache)?.as_vec(),
+  );
+  for item in rest {
+    if !vectors.contains_key(&item.path) {
+      vectors.insert(item.path.clone(), get_or_compute(&item.path, &mut cache)?.as_vec());
+    }
+  }
+  save_cache(config_dir, &cache)?;
+  Ok(vectors)
+}
+
+/// Reorders `play_next` (a stack popped from the end) so that, starting from
+/// `current`, each following song is the nearest remaining neighbor in
+/// descriptor space, L2-normalized per dimension across the library, instead
+/// of pure random ordering. Entries that share an underlying file (e.g.
+/// tracks expanded from the same CUE sheet) share a descriptor.
+pub fn smart_shuffle(
+  play_next: &[QueueItem],
+  current: &QueueItem,
+  config_dir: &str,
+) -> Result<Vec<QueueItem>, Box<dyn Error>> {
+  let mut vectors = vectors_for(current, play_next, config_dir)?;
+  normalize_l2(&mut vectors);
+
+  let mut order = greedy_order(current, play_next, &vectors);
+  // `current` is about to be pushed onto `history` by the caller, so it
+  // must not reappear in `play_next`.
+  order.remove(0);
+  // `play_next` is popped from the end, so the nearest song plays next.
+  order.reverse();
+  Ok(order)
+}
+
+/// Orders `queue` (given in the same pop-from-end stack convention as
+/// `play_next`) by acoustic similarity for `--smart-order`: starting from the
+/// song that would play first (the last element), each following song is the
+/// nearest remaining neighbor in z-scored descriptor space across the whole
+/// queue, instead of the plain alphabetical ordering `load_song_list` builds.
+pub fn order_by_similarity(
+  queue: &[QueueItem],
+  config_dir: &str,
+) -> Result<Vec<QueueItem>, Box<dyn Error>> {
+  let Some((anchor, rest)) = queue.split_last() else {
+    return Ok(queue.to_vec());
+  };
+
+  let mut vectors = vectors_for(anchor, rest, config_dir)?;
+  normalize_zscore(&mut vectors);
+
+  let mut order = greedy_order(anchor, rest, &vectors);
+  order.reverse();
+  Ok(order)
+}
+
+/// L2-normalizes each descriptor dimension across the whole set of vectors.
+fn normalize_l2(vectors: &mut HashMap<PathBuf, Vec<f32>>) {
+  let dims = vectors.values().next().map(|v| v.len()).unwrap_or(0);
+  for d in 0..dims {
+    let norm = vectors.values().map(|v| v[d] * v[d]).sum::<f32>().sqrt();
+    if norm > 0.0 {
+      for v in vectors.values_mut() {
+        v[d] /= norm;
+      }
+    }
+  }
+}
+
+/// Z-score-normalizes each descriptor dimension (subtract the mean, divide
+/// by the standard deviation) across the whole set of vectors.
+fn normalize_zscore(vectors: &mut HashMap<PathBuf, Vec<f32>>) {
+  let dims = vectors.values().next().map(|v| v.len()).unwrap_or(0);
+  let n = vectors.len().max(1) as f32;
+  for d in 0..dims {
+    let mean = vectors.values().map(|v| v[d]).sum::<f32>() / n;
+    let variance = vectors.values().map(|v| (v[d] - mean).powi(2)).sum::<f32>() / n;
+    let std_dev = variance.sqrt();
+    for v in vectors.values_mut() {
+      v[d] = if std_dev > 0.0 {
+        (v[d] - mean) / std_dev
+      } else {
+        0.0
+      };
+    }
+  }
+}
+
+fn euclidean_distance(a: &[f32], b: &[f32]) -> f32 {
+  a.iter()
+    .zip(b)
+    .map(|(x, y)| (x - y).powi(2))
+    .sum::<f32>()
+    .sqrt()
+}
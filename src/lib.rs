@@ -5,7 +5,15 @@ const HANN_WINDOW_SIZE: usize = 2048;
 const SUPPORTED_FORMATS: [&str; 5] = ["mp3", "flac", "ogg", "wav", "aac"];
 
 pub mod app;
+pub mod audio;
+pub mod cue;
+pub mod features;
+pub mod lyrics;
+pub mod playlists;
+pub mod resample;
 pub mod search;
 pub mod songs;
 pub mod spectrum;
+pub mod streaming;
+pub mod tags;
 pub mod ui;
@@ -0,0 +1,58 @@
+use std::{
+  fs,
+  path::Path,
+  time::Duration,
+};
+
+/// Time-synced lyrics parsed from a `.lrc` sidecar file.
+pub struct Lyrics {
+  lines: Vec<(Duration, String)>,
+}
+
+impl Lyrics {
+  /// Looks for a `.lrc` file with the same stem as `song` and parses it.
+  /// Returns `None` if no sidecar file exists or it contains no timed lines.
+  pub fn load(song: &Path) -> Option<Lyrics> {
+    let lrc_path = song.with_extension("lrc");
+    let contents = fs::read_to_string(lrc_path).ok()?;
+    let mut lines = contents
+      .lines()
+      .filter_map(parse_line)
+      .collect::<Vec<(Duration, String)>>();
+    if lines.is_empty() {
+      return None;
+    }
+    lines.sort_by_key(|(t, _)| *t);
+    Some(Lyrics { lines })
+  }
+
+  /// Returns the line whose timestamp is the greatest one at or before `pos`,
+  /// along with its index, for scrolling/highlighting purposes.
+  pub fn current_line(&self, pos: Duration) -> Option<(usize, &str)> {
+    self
+      .lines
+      .iter()
+      .enumerate()
+      .take_while(|(_, (t, _))| *t <= pos)
+      .last()
+      .map(|(i, (_, text))| (i, text.as_str()))
+  }
+
+  /// Returns all lyric lines, in order.
+  pub fn lines(&self) -> impl Iterator<Item = &str> {
+    self.lines.iter().map(|(_, text)| text.as_str())
+  }
+}
+
+/// Parses a single `[mm:ss.xx] line text` entry.
+/// Lines with no recognizable timestamp are skipped.
+fn parse_line(line: &str) -> Option<(Duration, String)> {
+  let line = line.trim();
+  let rest = line.strip_prefix('[')?;
+  let (tag, text) = rest.split_once(']')?;
+  let (mm, ss) = tag.split_once(':')?;
+  let mm: u64 = mm.trim().parse().ok()?;
+  let ss: f64 = ss.trim().parse().ok()?;
+  let millis = mm * 60_000 + (ss * 1000.0) as u64;
+  Some((Duration::from_millis(millis), text.trim().to_owned()))
+}
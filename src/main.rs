@@ -9,26 +9,67 @@ struct Args {
   /// Use the default color for the ui, instead of changing per-song.
   #[clap(short, long)]
   default_color: bool,
+  /// Serve the decoded audio to network clients at this address (e.g. 0.0.0.0:7878),
+  /// turning this instance into a tiny personal radio station.
+  #[clap(long)]
+  serve: Option<String>,
+  /// Connect to a `--serve` instance at this address and play/visualize its stream.
+  #[clap(long)]
+  listen: Option<String>,
+  /// Cap decoded audio to this sample rate (e.g. 48000), resampling down
+  /// files encoded above it.
+  #[clap(long)]
+  max_samplerate: Option<u32>,
+  /// Order the initial queue by acoustic similarity instead of alphabetically,
+  /// same descriptor/greedy-nearest-neighbor approach as the `S` smart
+  /// shuffle key (see `features::order_by_similarity`).
+  #[clap(long)]
+  smart_order: bool,
 }
 
 /// Handles parsing arguments, and then passing them to the app.
 fn main() -> Result<(), Box<dyn Error>> {
   let args = Args::parse();
 
+  if let Some(addr) = args.listen {
+    let res = tuitunes::app::run_client(&addr, args.default_color);
+    if let Err(e) = res {
+      println!("{:?}", e);
+    }
+    return Ok(());
+  }
 
-  let path = args.path.map(|p| {
-    if p.starts_with("https://") || p.starts_with("http://") {
+  let path = match args.path {
+    Some(p) if p.starts_with("https://") || p.starts_with("http://") => {
       println!("Looks like you passed in a HTTP URL, downloading...");
-      let path = tuitunes::songs::save_song_locally(&p).unwrap();
-      println!("Saved the file to disk, playing...");
-      path
-    } else {
+      match tuitunes::songs::save_song_locally(&p) {
+        Ok(path) => {
+          println!("Saved the file to disk, playing...");
+          Some(path)
+        }
+        Err(e) => {
+          println!("{:?}", e);
+          return Ok(());
+        }
+      }
+    }
+    Some(p) => {
       println!("Looks like you passed in a local path, playing...");
-      PathBuf::from_str(&p).unwrap()
+      Some(PathBuf::from_str(&p).unwrap())
     }
-  });
+    None => None,
+  };
 
-  let res = tuitunes::app::run(path, args.default_color);
+  let res = match args.serve {
+    Some(addr) => tuitunes::app::run_serving(
+      path,
+      args.default_color,
+      &addr,
+      args.max_samplerate,
+      args.smart_order,
+    ),
+    None => tuitunes::app::run(path, args.default_color, args.max_samplerate, args.smart_order),
+  };
   if let Err(e) = res {
     println!("{:?}", e);
   }
@@ -0,0 +1,68 @@
+use std::{error::Error, fs, path::PathBuf};
+
+use anyhow::anyhow;
+
+use crate::songs::QueueItem;
+
+/// Rejects a playlist name that could escape `playlists_dir` once joined
+/// with `.txt` (path separators, or a bare `..`), since `name` comes
+/// straight from keystrokes typed into the save-as prompt.
+fn check_name(name: &str) -> Result<(), Box<dyn Error>> {
+  if name.is_empty() || name == ".." || name.contains(['/', '\\']) {
+    return Err(anyhow!("invalid playlist name {:?}", name).into());
+  }
+  Ok(())
+}
+
+/// Directory (under the app's config dir) holding one file per named
+/// playlist, each a newline-separated list of paths, same as `songs.txt`.
+fn playlists_dir(config_dir: &str) -> Result<PathBuf, Box<dyn Error>> {
+  let dir = PathBuf::from(config_dir).join("playlists");
+  fs::create_dir_all(&dir)?;
+  Ok(dir)
+}
+
+/// Lists the names of saved playlists (filenames under the playlists dir,
+/// without their `.txt` extension), sorted alphabetically.
+pub fn list(config_dir: &str) -> Result<Vec<String>, Box<dyn Error>> {
+  let dir = playlists_dir(config_dir)?;
+  let mut names = dir
+    .read_dir()?
+    .filter_map(|e| e.ok())
+    .map(|e| e.path())
+    .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("txt"))
+    .filter_map(|p| p.file_stem().and_then(|s| s.to_str()).map(str::to_owned))
+    .collect::<Vec<String>>();
+  names.sort();
+  Ok(names)
+}
+
+/// Loads a named playlist's paths as whole-file queue entries. Like
+/// `songs.txt`, only plain paths are persisted, so any CUE offsets or
+/// per-track titles are not preserved across a save/load round trip.
+pub fn load(config_dir: &str, name: &str) -> Result<Vec<QueueItem>, Box<dyn Error>> {
+  check_name(name)?;
+  let path = playlists_dir(config_dir)?.join(format!("{name}.txt"));
+  let contents = fs::read_to_string(path)?;
+  Ok(
+    contents
+      .lines()
+      .filter(|l| !l.trim().is_empty())
+      .map(|l| QueueItem::whole(PathBuf::from(l)))
+      .collect(),
+  )
+}
+
+/// Saves `items`' paths as a new named playlist, overwriting any existing
+/// playlist with the same name.
+pub fn save(config_dir: &str, name: &str, items: &[QueueItem]) -> Result<(), Box<dyn Error>> {
+  check_name(name)?;
+  let path = playlists_dir(config_dir)?.join(format!("{name}.txt"));
+  let contents = items
+    .iter()
+    .map(|i| i.path.to_str().unwrap_or_default())
+    .collect::<Vec<&str>>()
+    .join("\n");
+  fs::write(path, contents)?;
+  Ok(())
+}
@@ -0,0 +1,193 @@
+use std::{collections::VecDeque, time::Duration};
+
+use rodio::Source;
+
+/// Linearly-interpolated downsampler: for output frame `j`, the source
+/// position is `pos = j * src_rate / dst_rate`; the output frame blends the
+/// source frames bracketing `pos` by `frac = pos - floor(pos)`. Used to cap
+/// high-sample-rate files (96k/192k) down to a configured maximum, so the
+/// FFT buffer in `spectrum::Analyzer` isn't sized for rates most output
+/// devices won't benefit from.
+pub struct Resampler<S: Source<Item = f32>> {
+  source: S,
+  channels: usize,
+  src_rate: u32,
+  dst_rate: u32,
+  prev_frame: Vec<f32>,
+  next_frame: Vec<f32>,
+  /// Frame index (in source frames) that `next_frame` holds.
+  next_frame_pos: u64,
+  /// Frame index one past the last frame the source actually produced;
+  /// `None` while still pulling real data.
+  end_frame_pos: Option<u64>,
+  /// Position of the next output frame, in (fractional) source frames.
+  pos: f64,
+  pending: VecDeque<f32>,
+}
+
+impl<S: Source<Item = f32>> Resampler<S> {
+  pub fn new(mut source: S, dst_rate: u32) -> Resampler<S> {
+    let channels = source.channels() as usize;
+    let src_rate = source.sample_rate().max(1);
+
+    let prev_frame = pull_frame(&mut source, channels).unwrap_or_else(|| vec![0.0; channels]);
+    let (next_frame, end_frame_pos) = match pull_frame(&mut source, channels) {
+      Some(frame) => (frame, None),
+      None => (prev_frame.clone(), Some(1)),
+    };
+
+    Resampler {
+      source,
+      channels,
+      src_rate,
+      dst_rate: dst_rate.max(1),
+      prev_frame,
+      next_frame,
+      next_frame_pos: 1,
+      end_frame_pos,
+      pos: 0.0,
+      pending: VecDeque::new(),
+    }
+  }
+
+  fn advance_to(&mut self, frame: u64) {
+    while self.end_frame_pos.is_none() && self.next_frame_pos <= frame {
+      match pull_frame(&mut self.source, self.channels) {
+        Some(fresh) => {
+          self.prev_frame = std::mem::replace(&mut self.next_frame, fresh);
+          self.next_frame_pos += 1;
+        }
+        None => {
+          // `next_frame` is the last real frame: hold it, as `new` does.
+          self.prev_frame = self.next_frame.clone();
+          self.end_frame_pos = Some(self.next_frame_pos + 1);
+        }
+      }
+    }
+  }
+
+  fn next_output_frame(&mut self) -> Option<Vec<f32>> {
+    let frame = self.pos.floor() as u64;
+    self.advance_to(frame);
+    if self.end_frame_pos.is_some_and(|end| frame >= end) {
+      return None;
+    }
+
+    let frac = (self.pos - frame as f64) as f32;
+    let out = self
+      .prev_frame
+      .iter()
+      .zip(self.next_frame.iter())
+      .map(|(a, b)| a * (1.0 - frac) + b * frac)
+      .collect::<Vec<f32>>();
+
+    self.pos += self.src_rate as f64 / self.dst_rate as f64;
+    Some(out)
+  }
+}
+
+/// Pulls one frame (one sample per channel) off `source`. Returns `None`
+/// only once the source is exhausted at a frame boundary; a partial trailing
+/// frame is padded with silence rather than dropped.
+fn pull_frame<S: Source<Item = f32>>(source: &mut S, channels: usize) -> Option<Vec<f32>> {
+  let first = source.next()?;
+  let mut frame = Vec::with_capacity(channels);
+  frame.push(first);
+  for _ in 1..channels {
+    frame.push(source.next().unwrap_or(0.0));
+  }
+  Some(frame)
+}
+
+impl<S: Source<Item = f32>> Iterator for Resampler<S> {
+  type Item = f32;
+
+  fn next(&mut self) -> Option<f32> {
+    if self.pending.is_empty() {
+      self.pending.extend(self.next_output_frame()?);
+    }
+    self.pending.pop_front()
+  }
+}
+
+impl<S: Source<Item = f32>> Source for Resampler<S> {
+  fn current_frame_len(&self) -> Option<usize> {
+    None
+  }
+
+  fn channels(&self) -> u16 {
+    self.channels as u16
+  }
+
+  fn sample_rate(&self) -> u32 {
+    self.dst_rate
+  }
+
+  fn total_duration(&self) -> Option<Duration> {
+    self.source.total_duration()
+  }
+}
+
+/// Either `source` untouched, or wrapped in a `Resampler`, depending on
+/// whether `--max-samplerate` applies to it. Kept as an enum rather than a
+/// boxed trait object so it composes with the generic `Analyzer::new`/
+/// `Sink::append` call sites without extra indirection.
+pub enum CappedSource<S: Source<Item = f32>> {
+  AsIs(S),
+  Resampled(Resampler<S>),
+}
+
+/// Wraps `source` in a `Resampler` down to `max_rate` if its native rate
+/// exceeds it, leaving it untouched otherwise.
+pub fn cap_sample_rate<S: Source<Item = f32>>(
+  source: S,
+  max_rate: Option<u32>,
+) -> CappedSource<S> {
+  match max_rate {
+    Some(max_rate) if source.sample_rate() > max_rate => {
+      CappedSource::Resampled(Resampler::new(source, max_rate))
+    }
+    _ => CappedSource::AsIs(source),
+  }
+}
+
+impl<S: Source<Item = f32>> Iterator for CappedSource<S> {
+  type Item = f32;
+
+  fn next(&mut self) -> Option<f32> {
+    match self {
+      CappedSource::AsIs(s) => s.next(),
+      CappedSource::Resampled(r) => r.next(),
+    }
+  }
+}
+
+impl<S: Source<Item = f32>> Source for CappedSource<S> {
+  fn current_frame_len(&self) -> Option<usize> {
+    match self {
+      CappedSource::AsIs(s) => s.current_frame_len(),
+      CappedSource::Resampled(r) => r.current_frame_len(),
+    }
+  }
+
+  fn channels(&self) -> u16 {
+    match self {
+      CappedSource::AsIs(s) => s.channels(),
+      CappedSource::Resampled(r) => r.channels(),
+    }
+  }
+
+  fn sample_rate(&self) -> u32 {
+    match self {
+      CappedSource::AsIs(s) => s.sample_rate(),
+      CappedSource::Resampled(r) => r.sample_rate(),
+    }
+  }
+
+  fn total_duration(&self) -> Option<Duration> {
+    match self {
+      CappedSource::AsIs(s) => s.total_duration(),
+      CappedSource::Resampled(r) => r.total_duration(),
+    }
+  }
+}
@@ -5,29 +5,115 @@ use std::{
   io::{BufReader, Cursor, Read},
   path::{Path, PathBuf},
   process::{Command, Stdio},
+  time::Duration,
 };
 
-use rodio::{Decoder, OutputStreamHandle, Sink, Source};
+use rodio::{source::Source, Decoder};
 
 use anyhow::anyhow;
 
+use crate::cue;
 use crate::spectrum::Analyzer;
+use crate::tags;
 
-/// Checks if the `song` path is a supported format, and loads it.
-pub fn load_app_and_sink<'a>(
-  song: &'a PathBuf,
-  stream_handle: &OutputStreamHandle,
-) -> Result<(Analyzer<'a>, Sink), Box<dyn Error>> {
+/// A single entry in the playback queue. Most songs simply point at their
+/// whole file; entries expanded from a `.cue` sheet share a `path` with
+/// their siblings but start (and stop) at different offsets into it.
+#[derive(Debug, Clone)]
+pub struct QueueItem {
+  pub path: PathBuf,
+  pub start: Duration,
+  /// Length of this track, if it ends before EOF (i.e. it's not the last
+  /// track pulled from a CUE sheet). Drives the CUE end-of-track check in
+  /// `app::run_app`, so it is left `None` outside of CUE playback rather
+  /// than being populated from embedded tags.
+  pub duration: Option<Duration>,
+  /// Title parsed from a CUE sheet or the file's embedded tags, shown in
+  /// place of the file stem.
+  pub title: Option<String>,
+  /// Artist parsed from the file's embedded tags (or a CUE `PERFORMER`), if
+  /// any.
+  pub artist: Option<String>,
+  /// Album parsed from the file's embedded tags (or a CUE album-level
+  /// `TITLE`), if any.
+  pub album: Option<String>,
+  /// Total track length read from embedded tags, used to render the
+  /// playback progress gauge. Unrelated to `duration` above.
+  pub tag_duration: Option<Duration>,
+}
+
+impl QueueItem {
+  /// A queue entry covering an entire audio file, with no CUE involved.
+  pub fn whole(path: PathBuf) -> QueueItem {
+    let tags = tags::read(&path);
+    QueueItem {
+      path,
+      start: Duration::ZERO,
+      duration: None,
+      title: tags.title,
+      artist: tags.artist,
+      album: tags.album,
+      tag_duration: tags.duration,
+    }
+  }
+
+  /// Tagged/CUE title, falling back to the file stem when unknown.
+  fn raw_title(&self) -> String {
+    match &self.title {
+      Some(title) => title.clone(),
+      None => self
+        .path
+        .file_stem()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_owned(),
+    }
+  }
+
+  /// Name to display in the up-next/history lists: "Artist – Title" when
+  /// the artist is known, otherwise just the title. The title falls back to
+  /// the file stem when untagged.
+  pub fn display_name(&self) -> String {
+    match &self.artist {
+      Some(artist) => format!("{artist} – {}", self.raw_title()),
+      None => self.raw_title(),
+    }
+  }
+
+  /// Richer "Artist — Title (Album)" label for the now-playing panel,
+  /// falling back progressively down to the plain title when tags are thin.
+  pub fn now_playing_label(&self) -> String {
+    let title = self.raw_title();
+    match (&self.artist, &self.album) {
+      (Some(artist), Some(album)) => format!("{artist} — {title} ({album})"),
+      (Some(artist), None) => format!("{artist} — {title}"),
+      (None, _) => title,
+    }
+  }
+}
+
+/// Checks if the `song` path is a supported format, and decodes it into an
+/// `Analyzer` for visualization, skipping to `start`. Playback itself is
+/// driven separately by the audio thread (see `crate::audio`), which decodes
+/// its own copy of the file. `max_samplerate` caps the rate fed to the FFT,
+/// same as the audio thread's own copy (see `crate::resample`).
+pub fn load_analyzer<'a>(
+  song: &'a Path,
+  start: Duration,
+  max_samplerate: Option<u32>,
+) -> Result<Analyzer<'a>, Box<dyn Error>> {
   if !has_supported_extension(song) {
     return Err(anyhow!("file {} is not a supported format", song.to_str().unwrap()).into());
   }
-  let sink = stream_handle.play_once(BufReader::new(File::open(song)?))?;
 
   let file = BufReader::new(File::open(song)?);
+  let source = Decoder::new(file)?.convert_samples::<f32>().skip_duration(start);
 
-  let app = crate::spectrum::Analyzer::new(Decoder::new(file)?.convert_samples::<f32>());
-
-  Ok((app, sink))
+  Ok(Analyzer::new(crate::resample::cap_sample_rate(
+    source,
+    max_samplerate,
+  )))
 }
 
 /// Helper function to determine is a file is a supported format.
@@ -37,38 +123,130 @@ fn has_supported_extension(path: &Path) -> bool {
     .any(|ext| path.extension().and_then(|e| e.to_str()) == Some(*ext))
 }
 
-/// Takes a list of song paths, and returns a list with just the names of the files.
-pub fn to_song_names(paths: &[PathBuf], rev: bool) -> Vec<&str> {
-  let p = paths
-    .iter()
-    .map(|b| b.file_stem().unwrap().to_str().unwrap());
+/// Helper function to determine if a file is a CUE sheet.
+fn is_cue_sheet(path: &Path) -> bool {
+  path.extension().and_then(|e| e.to_str()) == Some("cue")
+}
+
+/// Takes a list of queue entries, and returns a list with just their display names.
+pub fn to_song_names(items: &[QueueItem], rev: bool) -> Vec<String> {
+  let names = items.iter().map(QueueItem::display_name);
   if rev {
-    p.rev().take(20).collect::<Vec<&str>>()
+    names.rev().take(20).collect::<Vec<String>>()
   } else {
-    p.take(20).collect::<Vec<&str>>()
+    names.take(20).collect::<Vec<String>>()
   }
 }
 
 /// Checks the path, if it's a directory it loads all of the songs in it.
-/// Otherwise if its a file it will attempt to load it as a song.
-pub fn load_song_list(song_path: PathBuf) -> std::io::Result<Vec<PathBuf>> {
-  let mut s = if song_path.is_dir() {
-    song_path
+/// Otherwise if its a file it will attempt to load it as a song. `.cue`
+/// sheets are expanded into one queue entry per track; audio files that have
+/// a sibling `.cue` sheet are skipped in favor of that expansion.
+pub fn load_song_list(song_path: PathBuf) -> Result<Vec<QueueItem>, Box<dyn Error>> {
+  if song_path.is_dir() {
+    let mut paths = song_path
       .read_dir()?
       .filter_map(|e| e.ok())
-      .filter(|e| e.metadata().unwrap().is_file() && has_supported_extension(&e.path()))
       .map(|e| e.path())
-      .collect::<Vec<PathBuf>>()
+      .filter(|p| p.metadata().map(|m| m.is_file()).unwrap_or(false))
+      .filter(|p| has_supported_extension(p) || is_cue_sheet(p))
+      .collect::<Vec<PathBuf>>();
+    paths.sort();
+    paths.reverse();
+
+    let mut items = vec![];
+    for path in paths {
+      if is_cue_sheet(&path) {
+        items.extend(cue::expand_cue_sheet(&path)?);
+      } else if path.with_extension("cue").is_file() {
+        continue; // covered by its CUE sheet instead
+      } else {
+        items.push(QueueItem::whole(path));
+      }
+    }
+    Ok(items)
+  } else if is_cue_sheet(&song_path) {
+    cue::expand_cue_sheet(&song_path)
+  } else {
+    Ok(vec![QueueItem::whole(song_path)])
+  }
+}
+
+/// An external downloader needed to pull audio out of a streaming URL that a
+/// plain HTTP GET can't turn into an audio file directly.
+enum StreamingProvider {
+  YouTube,
+  Spotify,
+}
+
+/// Recognizes hosts that need `yt-dlp`/`spotdl` instead of a plain GET.
+fn streaming_provider(url: &str) -> Option<StreamingProvider> {
+  if url.contains("youtube.com") || url.contains("youtu.be") {
+    Some(StreamingProvider::YouTube)
+  } else if url.contains("open.spotify.com") {
+    Some(StreamingProvider::Spotify)
   } else {
-    vec![song_path]
+    None
+  }
+}
+
+/// Audio format `yt-dlp`/`spotdl` should extract to, configurable via
+/// `TUITUNES_AUDIO_FORMAT` (default `mp3`).
+fn preferred_audio_format() -> String {
+  env::var("TUITUNES_AUDIO_FORMAT").unwrap_or_else(|_| "mp3".to_owned())
+}
+
+/// Downloads `url` with the external tool appropriate for `provider`,
+/// extracting audio into the temp dir. Tool paths default to `yt-dlp`/
+/// `spotdl` on `$PATH`, overridable via `YT_DLP_PATH`/`SPOTDL_PATH`.
+fn download_via_external_tool(
+  url: &str,
+  provider: StreamingProvider,
+) -> Result<PathBuf, Box<dyn Error>> {
+  let (tool, env_var) = match provider {
+    StreamingProvider::YouTube => ("yt-dlp", "YT_DLP_PATH"),
+    StreamingProvider::Spotify => ("spotdl", "SPOTDL_PATH"),
   };
-  s.sort();
-  s.reverse();
-  Ok(s)
+  let tool = env::var(env_var).unwrap_or_else(|_| tool.to_owned());
+  let format = preferred_audio_format();
+
+  let mut out_path = temp_dir();
+  out_path.push(format!("downloaded_song.{}", format));
+
+  let status = Command::new(&tool)
+    .arg("--extract-audio")
+    .arg("--audio-format")
+    .arg(&format)
+    .arg("-o")
+    .arg(&out_path)
+    .arg(url)
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .status()?;
+
+  if !status.success() {
+    return Err(anyhow!("{} exited with {}", tool, status).into());
+  }
+  if !has_supported_extension(&out_path) {
+    return Err(anyhow!(
+      "{} produced an unsupported format: {:?}",
+      tool,
+      out_path.extension()
+    )
+    .into());
+  }
+
+  Ok(out_path)
 }
 
-/// Performs an HTTP request and saves the file to a temporary location.
+/// Performs an HTTP request and saves the file to a temporary location, or
+/// shells out to `yt-dlp`/`spotdl` first when `path` is a YouTube/Spotify
+/// link those tools understand but a plain GET can't fetch audio from.
 pub fn save_song_locally(path: &str) -> Result<PathBuf, Box<dyn Error>> {
+  if let Some(provider) = streaming_provider(path) {
+    return download_via_external_tool(path, provider);
+  }
+
   let resp = reqwest::blocking::get(path)?;
   let ext = resp
     .headers()
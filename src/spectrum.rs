@@ -1,3 +1,4 @@
+use rodio::source::Empty;
 use spectrum_analyzer::scaling::divide_by_N;
 use spectrum_analyzer::windows::hann_window;
 use spectrum_analyzer::{samples_fft_to_spectrum, FrequencyLimit};
@@ -18,10 +19,11 @@ impl<'a> Analyzer<'a> {
   where
     S: rodio::Source<Item = f32> + Send + 'static,
   {
+    let sample_rate = source.sample_rate();
     Analyzer {
       channels: source.channels() as u64,
-      sample_rate: source.sample_rate() as u32,
-      buf: vec![0.0; crate::TICK_RATE as usize * 4 * source.sample_rate() as usize / 1000],
+      sample_rate,
+      buf: vec![0.0; buf_len(sample_rate)],
       source: Box::new(source),
       spectrum: vec![("", 0.0); crate::NUM_BARS],
     }
@@ -42,6 +44,29 @@ impl<'a> Analyzer<'a> {
       }
     }
 
+    self.compute_spectrum();
+  }
+
+  /// Computes the spectrum directly from externally-supplied samples,
+  /// bypassing `source`. Used by analyzers created with `for_streaming`,
+  /// which receive already-decoded PCM over the network instead of
+  /// decoding a local file.
+  pub fn feed_samples(&mut self, samples: &[f32]) {
+    let buf = &mut self.buf[0..crate::HANN_WINDOW_SIZE];
+    let n = samples.len().min(buf.len());
+    buf[..n].copy_from_slice(&samples[..n]);
+    for d in buf[n..].iter_mut() {
+      *d = 0.0;
+    }
+
+    self.compute_spectrum();
+  }
+
+  fn compute_spectrum(&mut self) {
+    const F_MIN: f32 = 40.0;
+    const F_MAX: f32 = 5000.0;
+
+    let buf = &self.buf[0..crate::HANN_WINDOW_SIZE];
     let hann_window = hann_window(buf);
     // calc spectrum
     let spectrum_hann_window = samples_fft_to_spectrum(
@@ -50,16 +75,46 @@ impl<'a> Analyzer<'a> {
       // sampling rate
       self.sample_rate,
       // optional frequency limit: e.g. only interested in frequencies 50 <= f <= 150?
-      FrequencyLimit::Range(40.0, 5000.0),
+      FrequencyLimit::Range(F_MIN, F_MAX),
       // optional scale
       Some(&divide_by_N),
     )
     .unwrap();
 
+    // Bin frequencies logarithmically instead of linearly, so the 48 bars
+    // spread musical content evenly instead of cramming it into the first
+    // few bars and leaving the rest mostly empty.
     self.spectrum = vec![("", 0.0); crate::NUM_BARS];
+    let mut bin_counts = [0u32; crate::NUM_BARS];
     for (fr, fr_val) in spectrum_hann_window.data().iter() {
-      let bar = (fr.val() - 40.0) * crate::NUM_BARS as f32 / (5000.0 - 40.0);
-      self.spectrum[bar as usize].1 += fr_val.val()
+      let bar = (crate::NUM_BARS as f32 * (fr.val() / F_MIN).ln() / (F_MAX / F_MIN).ln()) as isize;
+      let bar = bar.clamp(0, crate::NUM_BARS as isize - 1) as usize;
+      self.spectrum[bar].1 += fr_val.val();
+      bin_counts[bar] += 1;
+    }
+
+    for (bar, count) in self.spectrum.iter_mut().zip(bin_counts.iter()) {
+      if *count > 0 {
+        bar.1 /= *count as f32;
+      }
+      // quiet content (small magnitudes) is otherwise invisible next to loud bars
+      bar.1 = 20.0 * (bar.1 + 1.0).log10();
+    }
+  }
+
+  /// Creates an analyzer that is fed externally via `feed_samples` instead
+  /// of pulling from a local `Source`, for the `--listen` network client
+  /// which receives already-decoded PCM. `sample_rate` comes straight off
+  /// the wire (a peer-supplied `TrackHeader`), so it isn't trusted as-is;
+  /// see `buf_len`.
+  pub fn for_streaming(sample_rate: u32, channels: u16) -> Analyzer<'a> {
+    let sample_rate = sample_rate.max(1);
+    Analyzer {
+      channels: channels as u64,
+      sample_rate,
+      buf: vec![0.0; buf_len(sample_rate)],
+      source: Box::new(Empty::<f32>::new()),
+      spectrum: vec![("", 0.0); crate::NUM_BARS],
     }
   }
 
@@ -67,4 +122,30 @@ impl<'a> Analyzer<'a> {
   pub fn get_spectrum(&self) -> &[(&'a str, f32)] {
     &self.spectrum
   }
+
+  /// Returns the analysis window filled by the most recent `sample_audio`
+  /// call: the first channel's sample of each frame it consumed, always
+  /// `HANN_WINDOW_SIZE` long (entries past the frames consumed keep older
+  /// data). Used to tee samples over the network while serving, see
+  /// `crate::streaming`.
+  pub fn last_samples(&self) -> &[f32] {
+    &self.buf[0..crate::HANN_WINDOW_SIZE]
+  }
+
+  pub fn sample_rate(&self) -> u32 {
+    self.sample_rate
+  }
+
+  pub fn channels(&self) -> u64 {
+    self.channels
+  }
+}
+
+/// Size of `Analyzer::buf` for a given sample rate: enough to hold
+/// `TICK_RATE` worth of samples, but never less than `HANN_WINDOW_SIZE` —
+/// `sample_audio`/`feed_samples` always slice `buf[0..HANN_WINDOW_SIZE]`, so
+/// an unusually low (or peer-supplied, and thus untrusted) sample rate must
+/// not be allowed to undersize the buffer and panic that slice.
+fn buf_len(sample_rate: u32) -> usize {
+  (crate::TICK_RATE as usize * 4 * sample_rate as usize / 1000).max(crate::HANN_WINDOW_SIZE)
 }
@@ -0,0 +1,358 @@
+use std::{
+  env,
+  error::Error,
+  io::{Read, Write},
+  net::{TcpListener, TcpStream},
+  sync::{
+    mpsc::{self, Receiver, Sender},
+    Arc, Mutex,
+  },
+  thread,
+  time::Duration,
+};
+
+use rodio::Source;
+
+/// Metadata sent once at the start of each track, before its sample frames.
+#[derive(Clone, Debug)]
+pub struct TrackHeader {
+  pub name: String,
+  pub sample_rate: u32,
+  pub channels: u16,
+}
+
+/// A single message on the wire: either a track boundary or a chunk of
+/// interleaved f32 samples for the track currently playing.
+#[derive(Clone)]
+pub enum StreamFrame {
+  Header(TrackHeader),
+  Samples(Vec<f32>),
+}
+
+const TAG_HEADER: u8 = 0;
+const TAG_SAMPLES: u8 = 1;
+
+/// Prefixed to every frame so a `TUITUNES_STREAM_KEY` mismatch between
+/// server and client is caught immediately at `read_frame` (garbled by the
+/// wrong XOR key, these 4 bytes essentially never happen to decode back to
+/// themselves) instead of silently feeding garbage lengths downstream.
+const FRAME_MAGIC: [u8; 4] = *b"TTFR";
+
+/// Upper bound on a `TrackHeader::name`'s wire length. Track names are
+/// filenames, so this is generous headroom, not a real limit.
+const MAX_NAME_LEN: usize = 4_096;
+/// Upper bound on the sample count of a single `StreamFrame::Samples`.
+/// A tick's worth of audio (see `Analyzer::last_samples`) is a few thousand
+/// samples at most, so this leaves generous headroom while still rejecting
+/// a corrupted/adversarial length prefix before it drives a multi-GB
+/// allocation.
+const MAX_FRAME_SAMPLES: usize = 1 << 20;
+
+/// Reads a `u32` length prefix and rejects it outright if it exceeds `max`,
+/// so a corrupted or adversarial length never drives an attacker-sized
+/// allocation.
+fn read_checked_len(r: &mut impl Read, max: usize) -> std::io::Result<usize> {
+  let mut len_buf = [0u8; 4];
+  r.read_exact(&mut len_buf)?;
+  let len = u32::from_be_bytes(len_buf) as usize;
+  if len > max {
+    return Err(std::io::Error::new(
+      std::io::ErrorKind::InvalidData,
+      format!("frame length {len} exceeds max {max}"),
+    ));
+  }
+  Ok(len)
+}
+
+fn write_frame(w: &mut impl Write, frame: &StreamFrame) -> std::io::Result<()> {
+  w.write_all(&FRAME_MAGIC)?;
+  match frame {
+    StreamFrame::Header(header) => {
+      w.write_all(&[TAG_HEADER])?;
+      let name = header.name.as_bytes();
+      w.write_all(&(name.len() as u32).to_be_bytes())?;
+      w.write_all(name)?;
+      w.write_all(&header.sample_rate.to_be_bytes())?;
+      w.write_all(&header.channels.to_be_bytes())
+    }
+    StreamFrame::Samples(samples) => {
+      w.write_all(&[TAG_SAMPLES])?;
+      w.write_all(&(samples.len() as u32).to_be_bytes())?;
+      for s in samples {
+        w.write_all(&s.to_be_bytes())?;
+      }
+      Ok(())
+    }
+  }
+}
+
+/// Reads a single length-prefixed `StreamFrame` off the wire.
+pub fn read_frame(r: &mut impl Read) -> std::io::Result<StreamFrame> {
+  let mut magic = [0u8; 4];
+  r.read_exact(&mut magic)?;
+  if magic != FRAME_MAGIC {
+    return Err(std::io::Error::new(
+      std::io::ErrorKind::InvalidData,
+      "bad frame magic (TUITUNES_STREAM_KEY mismatch between server and client?)",
+    ));
+  }
+
+  let mut tag = [0u8; 1];
+  r.read_exact(&mut tag)?;
+  match tag[0] {
+    TAG_HEADER => {
+      let len = read_checked_len(r, MAX_NAME_LEN)?;
+      let mut name_buf = vec![0u8; len];
+      r.read_exact(&mut name_buf)?;
+
+      let mut sample_rate_buf = [0u8; 4];
+      r.read_exact(&mut sample_rate_buf)?;
+      let mut channels_buf = [0u8; 2];
+      r.read_exact(&mut channels_buf)?;
+
+      Ok(StreamFrame::Header(TrackHeader {
+        name: String::from_utf8_lossy(&name_buf).into_owned(),
+        sample_rate: u32::from_be_bytes(sample_rate_buf),
+        channels: u16::from_be_bytes(channels_buf),
+      }))
+    }
+    _ => {
+      let len = read_checked_len(r, MAX_FRAME_SAMPLES)?;
+      let mut samples = Vec::with_capacity(len);
+      for _ in 0..len {
+        let mut buf = [0u8; 4];
+        r.read_exact(&mut buf)?;
+        samples.push(f32::from_be_bytes(buf));
+      }
+      Ok(StreamFrame::Samples(samples))
+    }
+  }
+}
+
+/// Shared key for the optional XOR cipher layer, from `TUITUNES_STREAM_KEY`.
+/// Unset (the default) means the transport is plain TCP.
+fn stream_key() -> Option<Vec<u8>> {
+  let key = env::var("TUITUNES_STREAM_KEY").ok()?;
+  if key.is_empty() {
+    None
+  } else {
+    Some(key.into_bytes())
+  }
+}
+
+/// Wraps a byte stream, XOR'ing every byte read/written with a repeating
+/// key. `write_frame`/`read_frame` only ever see `Read`/`Write`, so layering
+/// this underneath them (see `MaybeXor`) never touches the framing code —
+/// it's a transport concern, not a wire-format one. Not meant to be a real
+/// cipher, just enough obfuscation to keep casual packet sniffing out.
+struct XorStream<S> {
+  inner: S,
+  key: Vec<u8>,
+  read_pos: usize,
+  write_pos: usize,
+}
+
+impl<S> XorStream<S> {
+  fn new(inner: S, key: Vec<u8>) -> XorStream<S> {
+    XorStream {
+      inner,
+      key,
+      read_pos: 0,
+      write_pos: 0,
+    }
+  }
+}
+
+impl<S: Read> Read for XorStream<S> {
+  fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    let n = self.inner.read(buf)?;
+    for byte in &mut buf[..n] {
+      *byte ^= self.key[self.read_pos % self.key.len()];
+      self.read_pos += 1;
+    }
+    Ok(n)
+  }
+}
+
+impl<S: Write> Write for XorStream<S> {
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    let masked = buf
+      .iter()
+      .enumerate()
+      .map(|(i, b)| b ^ self.key[(self.write_pos + i) % self.key.len()])
+      .collect::<Vec<u8>>();
+    let n = self.inner.write(&masked)?;
+    self.write_pos += n;
+    Ok(n)
+  }
+
+  fn flush(&mut self) -> std::io::Result<()> {
+    self.inner.flush()
+  }
+}
+
+/// Picks between a plain stream and an `XorStream` at runtime (whether
+/// `TUITUNES_STREAM_KEY` is set), while still being a single concrete type
+/// `write_frame`/`read_frame` can be called on generically.
+enum MaybeXor<S> {
+  Plain(S),
+  Xor(XorStream<S>),
+}
+
+impl<S> MaybeXor<S> {
+  fn wrap(inner: S) -> MaybeXor<S> {
+    match stream_key() {
+      Some(key) => MaybeXor::Xor(XorStream::new(inner, key)),
+      None => MaybeXor::Plain(inner),
+    }
+  }
+}
+
+impl<S: Read> Read for MaybeXor<S> {
+  fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    match self {
+      MaybeXor::Plain(s) => s.read(buf),
+      MaybeXor::Xor(s) => s.read(buf),
+    }
+  }
+}
+
+impl<S: Write> Write for MaybeXor<S> {
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    match self {
+      MaybeXor::Plain(s) => s.write(buf),
+      MaybeXor::Xor(s) => s.write(buf),
+    }
+  }
+
+  fn flush(&mut self) -> std::io::Result<()> {
+    match self {
+      MaybeXor::Plain(s) => s.flush(),
+      MaybeXor::Xor(s) => s.flush(),
+    }
+  }
+}
+
+/// Accepts connections on `addr` and fans every published `StreamFrame` out
+/// to all currently-connected clients, so `--serve` mode can tee the
+/// decoded PCM already being consumed locally for playback/visualization.
+pub struct Broadcaster {
+  clients: Arc<Mutex<Vec<Sender<StreamFrame>>>>,
+}
+
+impl Broadcaster {
+  /// Binds `addr` and starts accepting client connections in the background.
+  pub fn serve(addr: &str) -> Result<Broadcaster, Box<dyn Error>> {
+    let listener = TcpListener::bind(addr)?;
+    let clients: Arc<Mutex<Vec<Sender<StreamFrame>>>> = Arc::new(Mutex::new(vec![]));
+
+    let accept_clients = clients.clone();
+    thread::spawn(move || {
+      for stream in listener.incoming().flatten() {
+        accept_clients.lock().unwrap().push(spawn_writer(stream));
+      }
+    });
+
+    Ok(Broadcaster { clients })
+  }
+
+  pub fn send_header(&self, header: TrackHeader) {
+    self.publish(StreamFrame::Header(header));
+  }
+
+  pub fn send_samples(&self, samples: &[f32]) {
+    self.publish(StreamFrame::Samples(samples.to_vec()));
+  }
+
+  fn publish(&self, frame: StreamFrame) {
+    let mut clients = self.clients.lock().unwrap();
+    clients.retain(|tx| tx.send(frame.clone()).is_ok());
+  }
+}
+
+/// Spawns the per-client thread that drains `frame`s onto its socket, and
+/// returns the sender side used to publish to it.
+fn spawn_writer(stream: TcpStream) -> Sender<StreamFrame> {
+  let mut stream = MaybeXor::wrap(stream);
+  let (tx, rx) = mpsc::channel::<StreamFrame>();
+  thread::spawn(move || {
+    for frame in rx {
+      if write_frame(&mut stream, &frame).is_err() {
+        break;
+      }
+    }
+  });
+  tx
+}
+
+/// Connects to a `--serve` instance at `addr` for `--listen` client mode.
+pub fn connect(addr: &str) -> Result<TcpStream, Box<dyn Error>> {
+  Ok(TcpStream::connect(addr)?)
+}
+
+/// Spawns a background thread that reads frames off `stream` and forwards
+/// them to the returned receiver, so the UI thread can drain them
+/// non-blockingly each tick instead of blocking on socket reads.
+pub fn spawn_reader(stream: TcpStream) -> Receiver<StreamFrame> {
+  let mut stream = MaybeXor::wrap(stream);
+  let (tx, rx) = mpsc::channel();
+  thread::spawn(move || loop {
+    match read_frame(&mut stream) {
+      Ok(frame) => {
+        if tx.send(frame).is_err() {
+          break;
+        }
+      }
+      Err(e) => {
+        eprintln!("stream read error, disconnecting: {e}");
+        break;
+      }
+    }
+  });
+  rx
+}
+
+/// A `rodio::Source` fed by an `mpsc::Receiver`, so the client's playback
+/// `Sink` can consume samples as they arrive over the network. It never
+/// "ends"; a momentary gap just plays silence rather than stopping the sink.
+pub struct NetworkSource {
+  rx: Receiver<f32>,
+  sample_rate: u32,
+  channels: u16,
+}
+
+impl NetworkSource {
+  pub fn new(rx: Receiver<f32>, sample_rate: u32, channels: u16) -> NetworkSource {
+    NetworkSource {
+      rx,
+      sample_rate,
+      channels,
+    }
+  }
+}
+
+impl Iterator for NetworkSource {
+  type Item = f32;
+
+  fn next(&mut self) -> Option<f32> {
+    Some(self.rx.recv_timeout(Duration::from_millis(50)).unwrap_or(0.0))
+  }
+}
+
+impl Source for NetworkSource {
+  fn current_frame_len(&self) -> Option<usize> {
+    None
+  }
+
+  fn channels(&self) -> u16 {
+    self.channels
+  }
+
+  fn sample_rate(&self) -> u32 {
+    self.sample_rate
+  }
+
+  fn total_duration(&self) -> Option<Duration> {
+    None
+  }
+}
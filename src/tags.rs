@@ -0,0 +1,396 @@
+use std::{
+  fs::File,
+  io::{Read, Seek, SeekFrom},
+  path::Path,
+  time::Duration,
+};
+
+/// Metadata read from a file's embedded tags (ID3v2 for mp3/aac, Vorbis
+/// comments for flac/ogg). Missing/unparseable files just yield all-`None`
+/// fields rather than erroring, since tags are optional sugar for display.
+#[derive(Debug, Clone, Default)]
+pub struct Tags {
+  pub title: Option<String>,
+  pub artist: Option<String>,
+  pub album: Option<String>,
+  pub duration: Option<Duration>,
+}
+
+/// Reads whatever embedded tags `path` has, based on its extension. Never
+/// fails; falls back to an empty `Tags` on any parse error or unsupported
+/// format, so callers can fall back to the file stem for display.
+pub fn read(path: &Path) -> Tags {
+  match path.extension().and_then(|e| e.to_str()) {
+    Some("mp3") | Some("aac") => read_id3(path),
+    Some("flac") => read_flac(path),
+    Some("ogg") => read_ogg(path),
+    _ => None,
+  }
+  .unwrap_or_default()
+}
+
+/// Reads the leading ID3v2 tag of an mp3/aac file, if present.
+fn read_id3(path: &Path) -> Option<Tags> {
+  let mut file = File::open(path).ok()?;
+  let mut header = [0u8; 10];
+  file.read_exact(&mut header).ok()?;
+  if &header[0..3] != b"ID3" {
+    return None;
+  }
+  let version = header[3];
+  let size = syncsafe_to_u32(&header[6..10]) as usize;
+
+  let mut body = vec![0u8; size];
+  file.read_exact(&mut body).ok()?;
+
+  let mut tags = Tags::default();
+  let mut pos = 0;
+  while pos + 10 <= body.len() {
+    let id = &body[pos..pos + 4];
+    if id == [0, 0, 0, 0] {
+      break; // padding
+    }
+
+    let frame_size = if version >= 4 {
+      syncsafe_to_u32(&body[pos + 4..pos + 8]) as usize
+    } else {
+      u32::from_be_bytes(body[pos + 4..pos + 8].try_into().ok()?) as usize
+    };
+    let content_start = pos + 10;
+    let content_end = content_start + frame_size;
+    if content_end > body.len() {
+      break;
+    }
+    let content = &body[content_start..content_end];
+
+    match id {
+      b"TIT2" => tags.title = decode_text_frame(content),
+      b"TPE1" => tags.artist = decode_text_frame(content),
+      b"TALB" => tags.album = decode_text_frame(content),
+      b"TLEN" => {
+        tags.duration = decode_text_frame(content)
+          .and_then(|s| s.trim().parse::<u64>().ok())
+          .map(Duration::from_millis);
+      }
+      _ => {}
+    }
+
+    pos = content_end;
+  }
+
+  Some(tags)
+}
+
+fn syncsafe_to_u32(bytes: &[u8]) -> u32 {
+  bytes.iter().fold(0u32, |acc, b| (acc << 7) | (*b as u32 & 0x7F))
+}
+
+/// Decodes an ID3 text frame (an encoding byte followed by the text) into a
+/// plain `String`, trimming the null terminator(s) ID3 pads text with.
+fn decode_text_frame(content: &[u8]) -> Option<String> {
+  if content.is_empty() {
+    return None;
+  }
+  let (encoding, text) = (content[0], &content[1..]);
+  let decoded = match encoding {
+    0 | 3 => String::from_utf8_lossy(text).into_owned(),
+    1 | 2 => decode_utf16_bytes(text),
+    _ => String::from_utf8_lossy(text).into_owned(),
+  };
+
+  let trimmed = decoded.trim_matches('\0').trim();
+  if trimmed.is_empty() {
+    None
+  } else {
+    Some(trimmed.to_owned())
+  }
+}
+
+/// Decodes UTF-16 (with or without a BOM) text, as used by ID3 encodings 1/2.
+fn decode_utf16_bytes(bytes: &[u8]) -> String {
+  // A BOM picks the byte order; without one (encoding 2) it is big-endian.
+  let (bytes, big_endian) = match bytes {
+    [0xFF, 0xFE, rest @ ..] => (rest, false),
+    [0xFE, 0xFF, rest @ ..] => (rest, true),
+    rest => (rest, true),
+  };
+  let units = bytes
+    .chunks_exact(2)
+    .map(|c| {
+      if big_endian {
+        u16::from_be_bytes([c[0], c[1]])
+      } else {
+        u16::from_le_bytes([c[0], c[1]])
+      }
+    })
+    .collect::<Vec<u16>>();
+  String::from_utf16_lossy(&units)
+}
+
+/// Reads the `VORBIS_COMMENT` metadata block of a flac file, if present.
+fn read_flac(path: &Path) -> Option<Tags> {
+  let mut file = File::open(path).ok()?;
+  let mut magic = [0u8; 4];
+  file.read_exact(&mut magic).ok()?;
+  if &magic != b"fLaC" {
+    return None;
+  }
+
+  let mut tags = Tags::default();
+  loop {
+    let mut block_header = [0u8; 4];
+    file.read_exact(&mut block_header).ok()?;
+    let is_last = block_header[0] & 0x80 != 0;
+    let block_type = block_header[0] & 0x7F;
+    let block_len =
+      u32::from_be_bytes([0, block_header[1], block_header[2], block_header[3]]) as usize;
+
+    if block_type == 4 {
+      let mut block = vec![0u8; block_len];
+      file.read_exact(&mut block).ok()?;
+      parse_vorbis_comments(&block, &mut tags);
+    } else {
+      file.seek(SeekFrom::Current(block_len as i64)).ok()?;
+    }
+
+    if is_last {
+      break;
+    }
+  }
+
+  Some(tags)
+}
+
+/// Reads the Vorbis comment header packet out of an Ogg-Vorbis file's
+/// leading pages, if present. Packets are reassembled from Ogg segments
+/// (a segment value of 255 means the packet continues into the next
+/// segment/page) rather than assuming the comment header fits in one page.
+fn read_ogg(path: &Path) -> Option<Tags> {
+  let mut file = File::open(path).ok()?;
+  let mut tags = Tags::default();
+  let mut packet = Vec::new();
+  let mut packets_seen = 0;
+
+  loop {
+    let mut page_header = [0u8; 27];
+    if file.read_exact(&mut page_header).is_err() {
+      break;
+    }
+    if &page_header[0..4] != b"OggS" {
+      return None;
+    }
+    let page_segments = page_header[26] as usize;
+    let mut seg_table = vec![0u8; page_segments];
+    file.read_exact(&mut seg_table).ok()?;
+
+    for &seg_len in &seg_table {
+      let mut segment = vec![0u8; seg_len as usize];
+      file.read_exact(&mut segment).ok()?;
+      packet.extend_from_slice(&segment);
+      if seg_len == 255 {
+        continue; // packet continues into the next segment
+      }
+
+      if packet.len() >= 7 && packet[0] == 3 && &packet[1..7] == b"vorbis" {
+        parse_vorbis_comments(&packet[7..], &mut tags);
+        return Some(tags);
+      }
+      packets_seen += 1;
+      packet.clear();
+      // The comment header is always the second packet; give up once we've
+      // passed it without a match.
+      if packets_seen >= 2 {
+        return Some(tags);
+      }
+    }
+  }
+
+  Some(tags)
+}
+
+fn parse_vorbis_comments(block: &[u8], tags: &mut Tags) {
+  let read_u32 = |b: &[u8]| u32::from_le_bytes(b.try_into().unwrap()) as usize;
+  if block.len() < 4 {
+    return;
+  }
+
+  let vendor_len = read_u32(&block[0..4]);
+  let mut pos = 4 + vendor_len;
+  if pos + 4 > block.len() {
+    return;
+  }
+  let comment_count = read_u32(&block[pos..pos + 4]);
+  pos += 4;
+
+  for _ in 0..comment_count {
+    if pos + 4 > block.len() {
+      break;
+    }
+    let len = read_u32(&block[pos..pos + 4]);
+    pos += 4;
+    if pos + len > block.len() {
+      break;
+    }
+    let comment = String::from_utf8_lossy(&block[pos..pos + len]);
+    pos += len;
+
+    if let Some((key, value)) = comment.split_once('=') {
+      match key.to_ascii_uppercase().as_str() {
+        "TITLE" => tags.title = Some(value.to_owned()),
+        "ARTIST" => tags.artist = Some(value.to_owned()),
+        "ALBUM" => tags.album = Some(value.to_owned()),
+        _ => {}
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn write_temp(name: &str, contents: &[u8]) -> std::path::PathBuf {
+    let mut path = std::env::temp_dir();
+    path.push(format!("tuitunes-test-{}-{name}", std::process::id()));
+    std::fs::write(&path, contents).unwrap();
+    path
+  }
+
+  fn syncsafe(n: u32) -> [u8; 4] {
+    [
+      ((n >> 21) & 0x7F) as u8,
+      ((n >> 14) & 0x7F) as u8,
+      ((n >> 7) & 0x7F) as u8,
+      (n & 0x7F) as u8,
+    ]
+  }
+
+  fn id3v2_3_frame(id: &[u8; 4], text: &str) -> Vec<u8> {
+    let mut content = vec![0u8]; // encoding 0 (ISO-8859-1/UTF-8 lossy)
+    content.extend_from_slice(text.as_bytes());
+    let mut frame = Vec::new();
+    frame.extend_from_slice(id);
+    frame.extend_from_slice(&(content.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&[0, 0]); // flags
+    frame.extend_from_slice(&content);
+    frame
+  }
+
+  #[test]
+  fn read_id3_parses_title_and_artist() {
+    let mut body = Vec::new();
+    body.extend_from_slice(&id3v2_3_frame(b"TIT2", "Test Title"));
+    body.extend_from_slice(&id3v2_3_frame(b"TPE1", "Test Artist"));
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"ID3");
+    bytes.extend_from_slice(&[3, 0, 0]); // version 3, revision 0, flags 0
+    bytes.extend_from_slice(&syncsafe(body.len() as u32));
+    bytes.extend_from_slice(&body);
+
+    let path = write_temp("tags.mp3", &bytes);
+    let tags = read(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(tags.title.as_deref(), Some("Test Title"));
+    assert_eq!(tags.artist.as_deref(), Some("Test Artist"));
+  }
+
+  #[test]
+  fn read_id3_rejects_missing_magic() {
+    let path = write_temp("bad.mp3", b"not an id3 tag at all");
+    let tags = read(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(tags.title.is_none());
+    assert!(tags.artist.is_none());
+  }
+
+  fn vorbis_comment_block(comments: &[(&str, &str)]) -> Vec<u8> {
+    let vendor = b"test vendor";
+    let mut block = Vec::new();
+    block.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    block.extend_from_slice(vendor);
+    block.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+    for (key, value) in comments {
+      let comment = format!("{key}={value}");
+      block.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+      block.extend_from_slice(comment.as_bytes());
+    }
+    block
+  }
+
+  #[test]
+  fn read_flac_parses_vorbis_comments() {
+    let block = vorbis_comment_block(&[("TITLE", "Test Title"), ("ARTIST", "Test Artist")]);
+    let len = block.len() as u32;
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(b"fLaC");
+    bytes.push(0x80 | 4); // last metadata block, type 4 (VORBIS_COMMENT)
+    bytes.extend_from_slice(&len.to_be_bytes()[1..]); // 24-bit big-endian length
+    bytes.extend_from_slice(&block);
+
+    let path = write_temp("tags.flac", &bytes);
+    let tags = read(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(tags.title.as_deref(), Some("Test Title"));
+    assert_eq!(tags.artist.as_deref(), Some("Test Artist"));
+  }
+
+  #[test]
+  fn read_flac_rejects_missing_magic() {
+    let path = write_temp("bad.flac", b"not a flac file");
+    let tags = read(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(tags.title.is_none());
+  }
+
+  fn ogg_page(payload: &[u8], seq: u32) -> Vec<u8> {
+    assert!(payload.len() < 255, "test helper only supports single-segment pages");
+    let mut page = Vec::new();
+    page.extend_from_slice(b"OggS");
+    page.push(0); // version
+    page.push(0); // header_type_flag
+    page.extend_from_slice(&[0u8; 8]); // granule_position
+    page.extend_from_slice(&[0u8; 4]); // serial_number
+    page.extend_from_slice(&seq.to_le_bytes()); // page_sequence_number
+    page.extend_from_slice(&[0u8; 4]); // checksum (unchecked by read_ogg)
+    page.push(1); // page_segments
+    page.push(payload.len() as u8); // segment_table
+    page.extend_from_slice(payload);
+    page
+  }
+
+  #[test]
+  fn read_ogg_parses_vorbis_comments_across_pages() {
+    let mut ident_payload = vec![1u8];
+    ident_payload.extend_from_slice(b"vorbis");
+    ident_payload.extend_from_slice(&[0u8; 4]); // dummy identification header body
+
+    let mut comment_payload = vec![3u8];
+    comment_payload.extend_from_slice(b"vorbis");
+    comment_payload.extend_from_slice(&vorbis_comment_block(&[("TITLE", "Test Title")]));
+
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&ogg_page(&ident_payload, 0));
+    bytes.extend_from_slice(&ogg_page(&comment_payload, 1));
+
+    let path = write_temp("tags.ogg", &bytes);
+    let tags = read(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(tags.title.as_deref(), Some("Test Title"));
+  }
+
+  #[test]
+  fn read_ogg_rejects_missing_capture_pattern() {
+    let path = write_temp("bad.ogg", b"not an ogg stream");
+    let tags = read(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(tags.title.is_none());
+  }
+}
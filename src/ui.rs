@@ -1,13 +1,14 @@
-use std::{collections::hash_map::DefaultHasher, hash::Hasher};
+use std::{collections::hash_map::DefaultHasher, hash::Hasher, time::Duration};
 
 use tui::{
   backend::Backend,
   layout::{Constraint, Direction, Layout, Rect},
   style::{Color, Modifier, Style},
-  widgets::{self, BarChart, Block, Borders, Clear, List, ListItem, Paragraph},
+  widgets::{self, BarChart, Block, Borders, Clear, Gauge, List, ListItem, ListState, Paragraph},
   Frame,
 };
 
+use crate::lyrics::Lyrics;
 use crate::spectrum::Analyzer;
 
 /// The main UI that the user sees.
@@ -15,9 +16,12 @@ pub fn main_ui<B: Backend>(
   f: &mut Frame<B>,
   analyzer: &Analyzer,
   song_name: &str,
-  up_next: &[&str],
-  history: &[&str],
+  up_next: &[String],
+  history: &[String],
   ui_color: Color,
+  lyrics: Option<&Lyrics>,
+  elapsed: Duration,
+  duration: Option<Duration>,
 ) {
   let data = analyzer
     .get_spectrum()
@@ -46,13 +50,37 @@ pub fn main_ui<B: Backend>(
   let lists_chunks = Layout::default()
     .direction(Direction::Horizontal)
     .margin(1)
-    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+    .constraints(
+      [
+        Constraint::Percentage(34),
+        Constraint::Percentage(33),
+        Constraint::Percentage(33),
+      ]
+      .as_ref(),
+    )
     .split(chunks[1]);
 
   f.render_widget(spectrum_visualizer(&data, ui_color), visualizer_chunk[0]);
-  f.render_widget(now_playing(song_name, ui_color), visualizer_chunk[1]);
+
+  if let Some(total) = duration {
+    let now_playing_chunks = Layout::default()
+      .direction(Direction::Vertical)
+      .constraints([Constraint::Min(3), Constraint::Length(3)].as_ref())
+      .split(visualizer_chunk[1]);
+    f.render_widget(now_playing(song_name, ui_color), now_playing_chunks[0]);
+    f.render_widget(
+      progress_gauge(elapsed, total, ui_color),
+      now_playing_chunks[1],
+    );
+  } else {
+    f.render_widget(now_playing(song_name, ui_color), visualizer_chunk[1]);
+  }
+
   f.render_widget(up_next_list(up_next, ui_color), lists_chunks[0]);
   f.render_widget(history_list(history, ui_color), lists_chunks[1]);
+
+  let (lyrics_list, mut lyrics_state) = lyrics_panel(lyrics, elapsed, ui_color);
+  f.render_stateful_widget(lyrics_list, lists_chunks[2], &mut lyrics_state);
 }
 
 /// Displays the spectrum visualizer for the currently playing song.
@@ -69,7 +97,7 @@ fn spectrum_visualizer<'a>(data: &'a [(&str, u64)], ui_color: Color) -> BarChart
 /// Displays info for the currently playing song, as well as controls.
 fn now_playing(song_name: &str, ui_color: Color) -> Paragraph {
   Paragraph::new(format!(
-    "{song_name}\n\nq: quit\nn: next\nb: back\np: play/pause\nr: restart song\na: add songs\ns: shuffle"
+    "{song_name}\n\nq: quit\nn: next\nb: back\np: play/pause\nr: restart song\n←/→: seek -/+5s\na: add songs\ns: shuffle\nS: smart shuffle\nl: playlists"
     ))
     .block(
       Block::default()
@@ -79,12 +107,30 @@ fn now_playing(song_name: &str, ui_color: Color) -> Paragraph {
     .style(Style::default().fg(ui_color))
 }
 
+/// Displays playback progress as "elapsed / total", driven by the track's
+/// embedded-tag duration (see `songs::QueueItem::tag_duration`).
+fn progress_gauge(elapsed: Duration, total: Duration, ui_color: Color) -> Gauge<'static> {
+  let ratio = if total.is_zero() {
+    0.0
+  } else {
+    (elapsed.as_secs_f64() / total.as_secs_f64()).clamp(0.0, 1.0)
+  };
+
+  let format_mmss = |d: Duration| format!("{}:{:02}", d.as_secs() / 60, d.as_secs() % 60);
+
+  Gauge::default()
+    .block(Block::default().borders(Borders::ALL))
+    .gauge_style(Style::default().fg(ui_color))
+    .ratio(ratio)
+    .label(format!("{} / {}", format_mmss(elapsed), format_mmss(total)))
+}
+
 /// Displays the list of upcoming songs.
-fn up_next_list<'a>(up_next: &'a [&str], ui_color: Color) -> List<'a> {
+fn up_next_list<'a>(up_next: &'a [String], ui_color: Color) -> List<'a> {
   List::new(
     up_next
       .iter()
-      .map(|s| ListItem::new(*s))
+      .map(|s| ListItem::new(s.as_str()))
       .collect::<Vec<ListItem>>(),
   )
   .block(Block::default().title("up-next").borders(Borders::ALL))
@@ -93,11 +139,11 @@ fn up_next_list<'a>(up_next: &'a [&str], ui_color: Color) -> List<'a> {
 }
 
 /// Displays the list of songs which have already played.
-fn history_list<'a>(history: &'a [&str], ui_color: Color) -> List<'a> {
+fn history_list<'a>(history: &'a [String], ui_color: Color) -> List<'a> {
   List::new(
     history
       .iter()
-      .map(|s| ListItem::new(*s))
+      .map(|s| ListItem::new(s.as_str()))
       .collect::<Vec<ListItem>>(),
   )
   .block(Block::default().title("history").borders(Borders::ALL))
@@ -105,6 +151,42 @@ fn history_list<'a>(history: &'a [&str], ui_color: Color) -> List<'a> {
   .highlight_style(Style::default().add_modifier(Modifier::ITALIC))
 }
 
+/// Displays the synced lyrics for the currently playing song, highlighting
+/// the last line whose timestamp is at or before the current playback
+/// position. The list scrolls only as far as needed to keep that line in
+/// view; it is not centered in the panel.
+/// Falls back to a placeholder when no lyrics are available.
+fn lyrics_panel<'a>(
+  lyrics: Option<&'a Lyrics>,
+  elapsed: Duration,
+  ui_color: Color,
+) -> (List<'a>, ListState) {
+  let block = Block::default().title("lyrics").borders(Borders::ALL);
+  let mut state = ListState::default();
+
+  let Some(lyrics) = lyrics else {
+    let list = List::new(vec![ListItem::new("no lyrics")])
+      .block(block)
+      .style(Style::default().fg(ui_color));
+    return (list, state);
+  };
+
+  let current = lyrics.current_line(elapsed).map(|(i, _)| i);
+  let items = lyrics
+    .lines()
+    .map(ListItem::new)
+    .collect::<Vec<ListItem>>();
+
+  state.select(current);
+
+  let list = List::new(items)
+    .block(block)
+    .style(Style::default().fg(ui_color))
+    .highlight_style(Style::default().fg(ui_color).add_modifier(Modifier::BOLD));
+
+  (list, state)
+}
+
 /// Creates a rectangle centered in the middle of the terminal.
 pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
   let popup_layout = Layout::default()
@@ -162,6 +244,48 @@ pub fn add_songs_popup<B: Backend>(
   f.render_widget(search_results, layout[1]);
 }
 
+/// Displays the popup used to pick a saved playlist to load or append, or to
+/// save the running queue as a new one, built like `add_songs_popup`.
+pub fn playlist_menu<B: Backend>(
+  f: &mut Frame<B>,
+  playlists: &[String],
+  state: &mut ListState,
+  new_name: &str,
+  ui_color: Color,
+) {
+  let area = centered_rect(50, 60, f.size());
+  let chunks = Layout::default()
+    .direction(Direction::Vertical)
+    .constraints([Constraint::Min(3), Constraint::Length(3)].as_ref())
+    .split(area);
+
+  let items = if playlists.is_empty() {
+    vec![ListItem::new("no saved playlists")]
+  } else {
+    playlists.iter().map(|s| ListItem::new(s.as_str())).collect()
+  };
+  let list = List::new(items)
+    .block(
+      Block::default()
+        .title("playlists (enter: load, a: append, s: save queue as new, esc: cancel)")
+        .borders(Borders::ALL),
+    )
+    .style(Style::default().fg(ui_color))
+    .highlight_style(Style::default().fg(ui_color).add_modifier(Modifier::BOLD));
+
+  let save_input = Paragraph::new(new_name)
+    .block(
+      Block::default()
+        .title("s: type a name for the running queue, enter to save")
+        .borders(Borders::ALL),
+    )
+    .style(Style::default().fg(ui_color));
+
+  f.render_widget(Clear, area);
+  f.render_stateful_widget(list, chunks[0], state);
+  f.render_widget(save_input, chunks[1]);
+}
+
 /// Gets the color of the UI.
 /// If use_default is set then it just uses yellow.
 /// Otherwise, it chooses a color by computing the hash of the song's name.